@@ -0,0 +1,267 @@
+use super::CliError;
+use crate::config::{
+    game_config_directory, game_config_template, profile_directory, serialize_game_config,
+    validate_game_config_file, Diagnostic, GameConfig, GameConfigFile,
+};
+use clap::Subcommand;
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+use tokio::{fs, process::Command};
+
+#[derive(Subcommand)]
+pub enum ConfigSubCommands {
+    /// Write a config with every field and its default value, commented out
+    Init {
+        app_id: String,
+
+        /// Overwrite the config if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Open the config in $EDITOR and validate it after it's saved
+    Edit { app_id: String },
+
+    /// Print the config the game would be launched with, after merging the defaults and profiles
+    Show { app_id: String },
+
+    /// Validate a single game config, or every config file if no app ID is given
+    Validate { app_id: Option<String> },
+
+    /// List the app IDs that have a config
+    List,
+}
+
+pub async fn run(subcommand: &ConfigSubCommands) -> Result<(), CliError> {
+    match subcommand {
+        ConfigSubCommands::Init { app_id, force } => init(app_id, *force).await,
+        ConfigSubCommands::Edit { app_id } => edit(app_id).await,
+        ConfigSubCommands::Show { app_id } => show(app_id).await,
+        ConfigSubCommands::Validate { app_id } => validate(app_id.as_deref()).await,
+        ConfigSubCommands::List => list().await,
+    }
+}
+
+fn game_config_path(app_id: &str) -> Result<PathBuf, CliError> {
+    Ok(game_config_directory()
+        .map_err(CliError::GameConfig)?
+        .join(format!("{app_id}.ron")))
+}
+
+async fn write_template(path: &Path) -> Result<(), CliError> {
+    let template = game_config_template().map_err(CliError::SerializeConfig)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|error| CliError::WriteConfigFile(error, parent.to_path_buf()))?;
+    }
+
+    fs::write(path, template)
+        .await
+        .map_err(|error| CliError::WriteConfigFile(error, path.to_path_buf()))
+}
+
+async fn init(app_id: &str, force: bool) -> Result<(), CliError> {
+    let path = game_config_path(app_id)?;
+
+    if path.exists() && !force {
+        return Err(CliError::ConfigFileExists(path));
+    }
+
+    write_template(&path).await?;
+
+    println!("Created {}", path.display());
+
+    Ok(())
+}
+
+async fn edit(app_id: &str) -> Result<(), CliError> {
+    let path = game_config_path(app_id)?;
+
+    if !path.exists() {
+        write_template(&path).await?;
+    }
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| String::from("vi"));
+
+    let editor_argv = shell_words::split(&editor)
+        .map_err(|_| CliError::RunEditor(io::ErrorKind::InvalidInput.into(), editor.clone()))?;
+
+    let Some((editor_program, editor_arguments)) = editor_argv.split_first() else {
+        return Err(CliError::RunEditor(
+            io::ErrorKind::InvalidInput.into(),
+            editor,
+        ));
+    };
+
+    loop {
+        Command::new(editor_program)
+            .args(editor_arguments)
+            .arg(&path)
+            .status()
+            .await
+            .map_err(|error| CliError::RunEditor(error, editor.clone()))?;
+
+        let diagnostics = validate_game_config_file(
+            GameConfigFile { path: path.clone() },
+            defaults_files().await?,
+        )
+        .await;
+
+        for diagnostic in &diagnostics {
+            eprintln!("{diagnostic}");
+        }
+
+        if !diagnostics.iter().any(Diagnostic::is_error) {
+            println!("{} is valid", path.display());
+
+            return Ok(());
+        }
+
+        eprint!("Edit the config again? [Y/n] ");
+
+        let _ = io::stderr().flush();
+        let mut answer = String::new();
+
+        io::stdin()
+            .lock()
+            .read_line(&mut answer)
+            .map_err(CliError::ReadStdin)?;
+
+        if answer.trim().eq_ignore_ascii_case("n") {
+            return Err(CliError::InvalidConfigFiles(1));
+        }
+    }
+}
+
+async fn show(app_id: &str) -> Result<(), CliError> {
+    let config_file = GameConfigFile::from_filename(app_id)
+        .await
+        .map_err(CliError::GameConfig)?;
+
+    let config = GameConfig::load(config_file)
+        .await
+        .map_err(CliError::GameConfig)?;
+
+    println!(
+        "{}",
+        serialize_game_config(&config).map_err(CliError::SerializeConfig)?
+    );
+
+    Ok(())
+}
+
+// The files a game config is merged on top of, so that it's validated the way it's launched.
+async fn defaults_files() -> Result<Vec<GameConfigFile>, CliError> {
+    Ok(GameConfigFile::defaults()
+        .await
+        .map_err(CliError::GameConfig)?
+        .into_iter()
+        .collect())
+}
+
+async fn validate(app_id: Option<&str>) -> Result<(), CliError> {
+    let mut files = Vec::new();
+
+    match app_id {
+        Some(app_id) => {
+            let path = game_config_path(app_id)?;
+
+            if !path.is_file() {
+                return Err(CliError::MissingConfigFile(path));
+            }
+
+            files.push((path, defaults_files().await?));
+        }
+        None => {
+            if let Some(defaults_file) = GameConfigFile::defaults()
+                .await
+                .map_err(CliError::GameConfig)?
+            {
+                files.push((defaults_file.path, Vec::new()));
+            }
+
+            if let Ok(profile_dir_path) = profile_directory() {
+                for path in ron_files_in(&profile_dir_path).await? {
+                    files.push((path, Vec::new()));
+                }
+            }
+
+            for path in
+                ron_files_in(&game_config_directory().map_err(CliError::GameConfig)?).await?
+            {
+                files.push((path, defaults_files().await?));
+            }
+        }
+    };
+
+    let mut invalid_config_files = 0;
+
+    for (path, underlying_files) in files {
+        let diagnostics =
+            validate_game_config_file(GameConfigFile { path: path.clone() }, underlying_files)
+                .await;
+
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            invalid_config_files += 1;
+
+            println!("error   {}", path.display());
+        } else {
+            println!("ok      {}", path.display());
+        }
+
+        for diagnostic in diagnostics {
+            println!("        {diagnostic}");
+        }
+    }
+
+    if invalid_config_files > 0 {
+        return Err(CliError::InvalidConfigFiles(invalid_config_files));
+    }
+
+    Ok(())
+}
+
+async fn list() -> Result<(), CliError> {
+    let game_config_dir_path = game_config_directory().map_err(CliError::GameConfig)?;
+
+    for path in ron_files_in(&game_config_dir_path).await? {
+        if let Some(app_id) = path.file_stem() {
+            println!("{}", app_id.to_string_lossy());
+        }
+    }
+
+    Ok(())
+}
+
+async fn ron_files_in(dir_path: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let mut entries = match fs::read_dir(dir_path).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(CliError::ReadConfigDirectory(error, dir_path.to_path_buf())),
+    };
+
+    let mut paths = Vec::new();
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|error| CliError::ReadConfigDirectory(error, dir_path.to_path_buf()))?
+    {
+        let path = entry.path();
+
+        if path.is_file() && path.extension().is_some_and(|extension| extension == "ron") {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
@@ -0,0 +1,526 @@
+use super::CliError;
+use crate::config::{GameConfig, GameConfigFile};
+use crate::process_output_log::{
+    diff_logs, find_logged_identifiers, find_sessions, open_log,
+    persistent_process_output_log_directory, prune_sessions, read_log_to_string, LogSession,
+    ProcessOutputLogKind, SessionExit, READABLE_TIMESTAMP_FORMAT,
+    RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY,
+};
+use chrono::Local;
+use clap::{Subcommand, ValueEnum};
+use nix::sys::signal::Signal;
+use std::{
+    io::{self, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+    time,
+};
+
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+const TAIL_CHUNK_SIZE: u64 = 8192;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    Combined,
+}
+
+impl From<LogStream> for ProcessOutputLogKind {
+    fn from(stream: LogStream) -> Self {
+        match stream {
+            LogStream::Stdout => Self::Stdout,
+            LogStream::Stderr => Self::Stderr,
+            LogStream::Combined => Self::Combined,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum LogsSubCommands {
+    /// List the games with persistent logs, or the sessions of a game with their size and exit
+    /// status. Session 1 is the newest
+    List { app_id: Option<String> },
+
+    /// Print the logs of a session, compressed logs are decompressed
+    Show {
+        app_id: String,
+
+        /// The session to show, 1 is the newest
+        #[arg(long, short, default_value_t = 1)]
+        session: usize,
+
+        /// Only print this log instead of every log of the session
+        #[arg(long, value_enum)]
+        stream: Option<LogStream>,
+    },
+
+    /// Print the end of the log of a running game, the most recently launched one if no app ID
+    /// is given. The combined log is used if it's written, the stderr log otherwise
+    Tail {
+        app_id: Option<String>,
+
+        /// Keep printing the lines the game writes until it exits
+        #[arg(long, short)]
+        follow: bool,
+
+        #[arg(long, short = 'n', default_value_t = 10)]
+        lines: usize,
+
+        #[arg(long, value_enum)]
+        stream: Option<LogStream>,
+    },
+
+    /// Print the directory the persistent logs are kept in
+    Path {
+        app_id: Option<String>,
+
+        /// Print the directory of the logs of running games instead
+        #[arg(long)]
+        runtime: bool,
+    },
+
+    /// Compare the logs of two sessions, after replacing times, addresses and thread IDs that
+    /// differ between every run
+    Diff {
+        app_id: String,
+
+        #[arg(default_value_t = 2)]
+        old_session: usize,
+
+        #[arg(default_value_t = 1)]
+        new_session: usize,
+
+        /// The combined log is compared if both sessions have it, the stderr log otherwise
+        #[arg(long, value_enum)]
+        stream: Option<LogStream>,
+    },
+
+    /// Remove and compress old logs following the game's `log_retention`, for every game if no
+    /// app ID is given
+    Prune { app_id: Option<String> },
+}
+
+pub async fn run(subcommand: &LogsSubCommands) -> Result<(), CliError> {
+    match subcommand {
+        LogsSubCommands::List { app_id } => list(app_id.as_deref()),
+        LogsSubCommands::Show {
+            app_id,
+            session,
+            stream,
+        } => show(app_id, *session, *stream),
+        LogsSubCommands::Tail {
+            app_id,
+            follow,
+            lines,
+            stream,
+        } => tail(app_id.as_deref(), *follow, *lines, *stream).await,
+        LogsSubCommands::Path { app_id, runtime } => path(app_id.as_deref(), *runtime),
+        LogsSubCommands::Diff {
+            app_id,
+            old_session,
+            new_session,
+            stream,
+        } => diff(app_id, *old_session, *new_session, *stream),
+        LogsSubCommands::Prune { app_id } => prune(app_id.as_deref()).await,
+    }
+}
+
+fn log_directory() -> Result<PathBuf, CliError> {
+    persistent_process_output_log_directory().map_err(CliError::ProcessOutputLog)
+}
+
+fn sessions_of(log_directory: &Path, app_id: &str) -> Result<Vec<LogSession>, CliError> {
+    find_sessions(&log_directory.join(app_id)).map_err(CliError::ProcessOutputLog)
+}
+
+fn nth_session(sessions: &[LogSession], app_id: &str, number: usize) -> Result<usize, CliError> {
+    if number == 0 || number > sessions.len() {
+        return Err(CliError::MissingLogSession(app_id.to_string(), number));
+    }
+
+    Ok(number - 1)
+}
+
+fn log_file<'a>(
+    session: &'a LogSession,
+    app_id: &str,
+    kind: ProcessOutputLogKind,
+) -> Result<&'a Path, CliError> {
+    session.log_file(kind).ok_or_else(|| {
+        CliError::MissingLog(
+            app_id.to_string(),
+            session
+                .timestamp
+                .format(READABLE_TIMESTAMP_FORMAT)
+                .to_string(),
+            kind.as_file_extension().to_string(),
+        )
+    })
+}
+
+fn default_kind(sessions: &[&LogSession], stream: Option<LogStream>) -> ProcessOutputLogKind {
+    match stream {
+        Some(stream) => stream.into(),
+        None if sessions
+            .iter()
+            .all(|session| session.log_file(ProcessOutputLogKind::Combined).is_some()) =>
+        {
+            ProcessOutputLogKind::Combined
+        }
+        None => ProcessOutputLogKind::Stderr,
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+fn format_exit_status(exit: Option<SessionExit>) -> String {
+    match exit {
+        Some(SessionExit::Code(exit_code)) => format!("exit {exit_code}"),
+        Some(SessionExit::Signal(signal)) => Signal::try_from(signal).map_or_else(
+            |_| format!("signal {signal}"),
+            |signal| format!("killed by {signal}"),
+        ),
+        None => String::from("-"),
+    }
+}
+
+fn list(app_id: Option<&str>) -> Result<(), CliError> {
+    let log_directory = log_directory()?;
+
+    let Some(app_id) = app_id else {
+        for app_id in find_logged_identifiers(&log_directory).map_err(CliError::ProcessOutputLog)? {
+            let sessions = sessions_of(&log_directory, &app_id)?;
+            let size = sessions
+                .iter()
+                .map(LogSession::size)
+                .sum::<Result<u64, _>>()
+                .map_err(CliError::ProcessOutputLog)?;
+            let newest = sessions.first().map_or_else(String::new, |session| {
+                format!("newest {}", session.timestamp.format("%Y-%m-%d %H:%M:%S"))
+            });
+
+            println!(
+                "{app_id:<12}{:>4} session(s){:>12}  {newest}",
+                sessions.len(),
+                format_size(size)
+            );
+        }
+
+        return Ok(());
+    };
+
+    for (index, session) in sessions_of(&log_directory, app_id)?.iter().enumerate() {
+        let size = session.size().map_err(CliError::ProcessOutputLog)?;
+        let exit = session.exit().map_err(CliError::ProcessOutputLog)?;
+
+        println!(
+            "{:>4}  {}{:>12}  {}",
+            index + 1,
+            session.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            format_size(size),
+            format_exit_status(exit)
+        );
+    }
+
+    Ok(())
+}
+
+fn print_log(path: &Path) -> Result<(), CliError> {
+    let mut log = open_log(path).map_err(CliError::ProcessOutputLog)?;
+
+    io::copy(&mut log, &mut io::stdout().lock()).map_err(CliError::WriteStdout)?;
+
+    Ok(())
+}
+
+fn show(app_id: &str, session_number: usize, stream: Option<LogStream>) -> Result<(), CliError> {
+    let sessions = sessions_of(&log_directory()?, app_id)?;
+    let session = &sessions[nth_session(&sessions, app_id, session_number)?];
+
+    if let Some(stream) = stream {
+        return print_log(log_file(session, app_id, stream.into())?);
+    }
+
+    for (index, path) in session.log_files().enumerate() {
+        if index > 0 {
+            println!();
+        }
+
+        println!(
+            "==> {} <==",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+
+        print_log(path)?;
+    }
+
+    Ok(())
+}
+
+fn last_lines(log: &str, count: usize) -> &str {
+    let trimmed_log = log.strip_suffix('\n').unwrap_or(log);
+
+    match trimmed_log
+        .rmatch_indices('\n')
+        .nth(count.saturating_sub(1))
+    {
+        Some((index, _)) if count > 0 => &log[index + 1..],
+        _ if count > 0 => log,
+        _ => "",
+    }
+}
+
+// Reads backwards from the end until it has enough lines, so that the start of a long log isn't
+// read. Returns the lines and where the log ended.
+async fn read_last_lines(path: &Path, count: usize) -> Result<(String, u64), CliError> {
+    let read_error = |error| CliError::ReadLog(error, path.to_path_buf());
+
+    let mut log = File::open(path).await.map_err(read_error)?;
+    let end = log.seek(SeekFrom::End(0)).await.map_err(read_error)?;
+
+    let mut start = end;
+    let mut tail = Vec::new();
+
+    // One more newline than lines, since the log usually ends with one.
+    while start > 0 && tail.iter().filter(|&&byte| byte == b'\n').count() <= count {
+        let chunk_size = start.min(TAIL_CHUNK_SIZE);
+        let mut chunk = vec![0; chunk_size as usize];
+
+        start -= chunk_size;
+
+        log.seek(SeekFrom::Start(start)).await.map_err(read_error)?;
+        log.read_exact(&mut chunk).await.map_err(read_error)?;
+
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+    }
+
+    Ok((
+        last_lines(&String::from_utf8_lossy(&tail), count).to_string(),
+        end,
+    ))
+}
+
+// Follows until the log is removed, the launching gtnkr removes it once the game exited.
+async fn follow_log(path: &Path, offset: u64) -> Result<(), CliError> {
+    let read_error = |error| CliError::ReadLog(error, path.to_path_buf());
+
+    let mut log = File::open(path).await.map_err(read_error)?;
+    let mut buffer = vec![0; 8192];
+
+    log.seek(SeekFrom::Start(offset))
+        .await
+        .map_err(read_error)?;
+
+    loop {
+        let read = log.read(&mut buffer).await.map_err(read_error)?;
+
+        if read > 0 {
+            let mut stdout = io::stdout().lock();
+
+            stdout
+                .write_all(&buffer[..read])
+                .and_then(|_| stdout.flush())
+                .map_err(CliError::WriteStdout)?;
+
+            continue;
+        }
+
+        if !path.exists() {
+            return Ok(());
+        }
+
+        time::sleep(FOLLOW_INTERVAL).await;
+    }
+}
+
+async fn tail(
+    app_id: Option<&str>,
+    follow: bool,
+    lines: usize,
+    stream: Option<LogStream>,
+) -> Result<(), CliError> {
+    let runtime_directory = RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY.as_path();
+
+    let app_ids = match app_id {
+        Some(app_id) => vec![app_id.to_string()],
+        None => find_logged_identifiers(runtime_directory).map_err(CliError::ProcessOutputLog)?,
+    };
+
+    let mut running_sessions = Vec::new();
+
+    // Sessions that only have their metadata left belong to launches that already exited.
+    for app_id in app_ids {
+        if let Some(session) = sessions_of(runtime_directory, &app_id)?
+            .into_iter()
+            .find(|session| session.log_files().next().is_some())
+        {
+            running_sessions.push((app_id, session));
+        }
+    }
+
+    let Some((app_id, session)) = running_sessions
+        .into_iter()
+        .max_by_key(|(_, session)| session.timestamp)
+    else {
+        return Err(CliError::NoRunningGame(runtime_directory.to_path_buf()));
+    };
+
+    let path = log_file(&session, &app_id, default_kind(&[&session], stream))?;
+
+    let (last_lines, end) = read_last_lines(path, lines).await?;
+
+    print!("{last_lines}");
+
+    if follow {
+        follow_log(path, end).await?;
+    }
+
+    Ok(())
+}
+
+fn path(app_id: Option<&str>, runtime: bool) -> Result<(), CliError> {
+    let log_directory = if runtime {
+        RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY.to_path_buf()
+    } else {
+        log_directory()?
+    };
+
+    match app_id {
+        Some(app_id) => println!("{}", log_directory.join(app_id).display()),
+        None => println!("{}", log_directory.display()),
+    }
+
+    Ok(())
+}
+
+fn diff(
+    app_id: &str,
+    old_session_number: usize,
+    new_session_number: usize,
+    stream: Option<LogStream>,
+) -> Result<(), CliError> {
+    let sessions = sessions_of(&log_directory()?, app_id)?;
+    let old_session = &sessions[nth_session(&sessions, app_id, old_session_number)?];
+    let new_session = &sessions[nth_session(&sessions, app_id, new_session_number)?];
+
+    let kind = default_kind(&[old_session, new_session], stream);
+    let old_path = log_file(old_session, app_id, kind)?;
+    let new_path = log_file(new_session, app_id, kind)?;
+
+    let read = |path| read_log_to_string(path).map_err(CliError::ProcessOutputLog);
+    let name = |path: &Path| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    let diff = diff_logs(
+        &read(old_path)?,
+        &read(new_path)?,
+        &name(old_path),
+        &name(new_path),
+    );
+
+    if diff.is_empty() {
+        eprintln!("The logs are the same after normalizing");
+    } else {
+        print!("{diff}");
+    }
+
+    Ok(())
+}
+
+async fn prune(app_id: Option<&str>) -> Result<(), CliError> {
+    let log_directory = log_directory()?;
+
+    let app_ids = match app_id {
+        Some(app_id) => vec![app_id.to_string()],
+        None => find_logged_identifiers(&log_directory).map_err(CliError::ProcessOutputLog)?,
+    };
+
+    let now = Local::now().naive_local();
+
+    for app_id in app_ids {
+        let config_file = GameConfigFile::from_filename(&app_id)
+            .await
+            .map_err(CliError::GameConfig)?;
+        let config = GameConfig::load(config_file)
+            .await
+            .map_err(CliError::GameConfig)?;
+
+        let report = prune_sessions(&log_directory.join(&app_id), &config.log_retention, now)
+            .map_err(CliError::ProcessOutputLog)?;
+
+        println!(
+            "{app_id}: removed {} session(s), compressed {} file(s), freed {}",
+            report.removed_sessions,
+            report.compressed_files,
+            format_size(report.freed_bytes)
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_size, last_lines, read_last_lines};
+    use color_eyre::eyre;
+    use tempdir::TempDir;
+    use tokio::fs;
+
+    #[test]
+    fn take_the_last_lines_and_format_sizes() {
+        assert_eq!(last_lines("a\nb\nc\n", 2), "b\nc\n");
+        assert_eq!(last_lines("a\nb\nc", 2), "b\nc");
+        assert_eq!(last_lines("a\nb\n", 5), "a\nb\n");
+        assert_eq!(last_lines("a\nb\n", 0), "");
+
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[tokio::test]
+    async fn read_the_last_lines_from_the_end() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("read_the_last_lines_from_the_end")?;
+        let path = temp_dir.path().join("outlog");
+
+        // Longer than a few chunks.
+        let log: String = (0..5000).map(|line| format!("line {line}\n")).collect();
+
+        fs::write(&path, &log).await?;
+
+        assert_eq!(
+            read_last_lines(&path, 2).await?,
+            (String::from("line 4998\nline 4999\n"), log.len() as u64)
+        );
+        assert_eq!(read_last_lines(&path, 10000).await?.0, log);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+mod config;
+mod logs;
+mod vulkan;
+
+use crate::config::{GameConfigError, VulkanDriverError};
+use crate::game_launcher::{GameLauncher, GameLauncherError, LaunchOptions, OutputTee};
+use crate::process_output_log::ProcessOutputLogError;
+use crate::steam_launch_command::SteamLaunchCommand;
+use clap::{Parser, Subcommand};
+use config::ConfigSubCommands;
+use logs::LogsSubCommands;
+use regex::Regex;
+use std::{io, path::PathBuf, process::ExitStatus};
+use vulkan::VulkanSubCommands;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error(transparent)]
+    GameLauncher(GameLauncherError),
+
+    #[error(transparent)]
+    GameConfig(GameConfigError),
+
+    #[error(transparent)]
+    VulkanDriver(VulkanDriverError),
+
+    #[error(transparent)]
+    ProcessOutputLog(ProcessOutputLogError),
+
+    #[error("The config file `{0}` already exists, use --force to overwrite it")]
+    ConfigFileExists(PathBuf),
+
+    #[error("The config file `{0}` doesn't exist")]
+    MissingConfigFile(PathBuf),
+
+    #[error("IO error while attempting to write the config file `{1:#?}`, see: {0:#?}")]
+    WriteConfigFile(io::Error, PathBuf),
+
+    #[error("IO error while attempting to read the config directory `{1:#?}`, see: {0:#?}")]
+    ReadConfigDirectory(io::Error, PathBuf),
+
+    #[error("Failed to serialize the config, see: {0}")]
+    SerializeConfig(ron::Error),
+
+    #[error("Failed to run the editor `{1}`, see: {0:#?}")]
+    RunEditor(io::Error, String),
+
+    #[error("The game `{0}` doesn't have a log session #{1}, see `gtnkr logs list {0}`")]
+    MissingLogSession(String, usize),
+
+    #[error("The log session {1} of the game `{0}` doesn't have a `.{2}` log")]
+    MissingLog(String, String, String),
+
+    #[error("IO error while attempting to read the log `{1:#?}`, see: {0:#?}")]
+    ReadLog(io::Error, PathBuf),
+
+    #[error("No game is running, there are no logs in `{0}`")]
+    NoRunningGame(PathBuf),
+
+    #[error("IO error while attempting to write to stdout, see: {0:#?}")]
+    WriteStdout(io::Error),
+
+    #[error("IO error while attempting to read from stdin, see: {0:#?}")]
+    ReadStdin(io::Error),
+
+    #[error("{0} config file(s) are invalid")]
+    InvalidConfigFiles(usize),
+}
+
+#[derive(Parser)]
+#[command(version, about)]
+#[command(propagate_version = true)]
+struct Cli {
+    #[command(subcommand)]
+    subcommand: SubCommands,
+}
+
+#[derive(Subcommand)]
+enum SubCommands {
+    Launch {
+        #[arg(value_parser = launch_subcommand_parser, long, short, env = "STEAM_LAUNCH_CMD")]
+        steam_launch_command: SteamLaunchCommand,
+
+        #[arg(long, short, env = "LOG_LAUNCH_CMD_OUTPUT")]
+        log_output: bool,
+
+        /// Also write a log with the lines of stdout and stderr interleaved, each prefixed with
+        /// the time since launch and its stream
+        #[arg(long, env = "LOG_LAUNCH_CMD_COMBINED")]
+        combined_log: bool,
+
+        /// Also print the game's stdout and stderr while it's running, colored by stream
+        #[arg(long, conflicts_with = "dry_run")]
+        tee: bool,
+
+        /// Only print the lines that match this regex with --tee, e.g. ':(err|fixme):' for Wine
+        #[arg(long, requires = "tee", value_parser = tee_filter_parser)]
+        tee_filter: Option<Regex>,
+
+        /// Print what would be executed instead of launching the game. Still runs the read-only
+        /// probes the plan depends on: `gamescope --help` and `--version`, and the resolution
+        /// lookup (compositor IPC, `xrandr` or `wlr-randr`)
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Print the dry run as JSON
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+
+    /// Print the command, wrappers and environment the game would be launched with
+    PrintCommand {
+        #[arg(value_parser = launch_subcommand_parser, long, short, env = "STEAM_LAUNCH_CMD")]
+        steam_launch_command: SteamLaunchCommand,
+
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Create, edit and validate game configs
+    Config {
+        #[command(subcommand)]
+        subcommand: ConfigSubCommands,
+    },
+
+    /// Inspect the installed Vulkan drivers
+    Vulkan {
+        #[command(subcommand)]
+        subcommand: VulkanSubCommands,
+    },
+
+    /// Browse, compare and prune the output logs
+    Logs {
+        #[command(subcommand)]
+        subcommand: LogsSubCommands,
+    },
+}
+
+fn launch_subcommand_parser(string: &str) -> Result<SteamLaunchCommand, String> {
+    SteamLaunchCommand::parse(string).map_err(|error| {
+        format!("Provided argument is not a valid Steam launch %command%. {error}")
+    })
+}
+
+fn tee_filter_parser(string: &str) -> Result<Regex, String> {
+    Regex::new(string).map_err(|error| format!("Provided argument is not a valid regex. {error}"))
+}
+
+// Returns the exit status of the game if one was launched, gtnkr should exit the same way.
+pub async fn run() -> Result<Option<ExitStatus>, CliError> {
+    let commands = Cli::parse();
+
+    let exit_status = match &commands.subcommand {
+        SubCommands::Launch {
+            steam_launch_command,
+            dry_run: true,
+            json,
+            ..
+        }
+        | SubCommands::PrintCommand {
+            steam_launch_command,
+            json,
+        } => {
+            print_launch_plan(steam_launch_command, *json)
+                .await
+                .map_err(CliError::GameLauncher)?;
+
+            None
+        }
+        SubCommands::Launch {
+            steam_launch_command,
+            log_output,
+            combined_log,
+            tee,
+            tee_filter,
+            dry_run: false,
+            ..
+        } => Some(
+            GameLauncher::launch_by_command(
+                steam_launch_command,
+                &steam_launch_command.app_id.to_string(),
+                LaunchOptions {
+                    persistent_output_log: *log_output,
+                    combined_output_log: *combined_log,
+                    tee: tee.then(|| OutputTee::new(tee_filter.clone())),
+                },
+            )
+            .await
+            .map_err(CliError::GameLauncher)?,
+        ),
+        SubCommands::Config { subcommand } => {
+            config::run(subcommand).await?;
+
+            None
+        }
+        SubCommands::Vulkan { subcommand } => {
+            vulkan::run(subcommand).await?;
+
+            None
+        }
+        SubCommands::Logs { subcommand } => {
+            logs::run(subcommand).await?;
+
+            None
+        }
+    };
+
+    Ok(exit_status)
+}
+
+async fn print_launch_plan(
+    steam_launch_command: &SteamLaunchCommand,
+    json: bool,
+) -> Result<(), GameLauncherError> {
+    let plan = GameLauncher::plan(
+        steam_launch_command,
+        &steam_launch_command.app_id.to_string(),
+    )
+    .await?;
+
+    if json {
+        println!("{}", plan.to_json()?);
+    } else {
+        println!("{plan}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use super::CliError;
+use crate::config::{discover_icd_manifests, icd_directories};
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub enum VulkanSubCommands {
+    /// List the installed Vulkan drivers and the `vulkan_driver` value that selects them
+    List,
+}
+
+pub async fn run(subcommand: &VulkanSubCommands) -> Result<(), CliError> {
+    match subcommand {
+        VulkanSubCommands::List => list(),
+    }
+}
+
+fn list() -> Result<(), CliError> {
+    let directories = icd_directories();
+    let manifests = discover_icd_manifests(&directories).map_err(CliError::VulkanDriver)?;
+
+    if manifests.is_empty() {
+        eprintln!("Couldn't find any Vulkan ICD manifests, searched:");
+
+        for directory in directories {
+            eprintln!("    {}", directory.display());
+        }
+
+        return Ok(());
+    }
+
+    // Drivers without a variant can still be selected with `Custom("<manifest path>")`.
+    for manifest in manifests {
+        let driver = manifest
+            .driver
+            .map_or_else(|| String::from("Custom"), |driver| format!("{driver:?}"));
+
+        println!(
+            "{driver:<10}{:<10}{}  ({})",
+            manifest.api_version.as_deref().unwrap_or("-"),
+            manifest.path.display(),
+            manifest.library_path.display()
+        );
+    }
+
+    Ok(())
+}
@@ -1,25 +1,53 @@
 use super::GameConfigError;
 use nix::unistd::{Uid, User};
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 use tokio::{fs::OpenOptions, io, io::AsyncReadExt};
 
-fn get_linux_username() -> String {
+fn get_linux_username() -> Result<String, GameConfigError> {
     let uid = Uid::current();
 
     match User::from_uid(uid) {
-        Err(error) => {
-            panic!("Failed to get [nix::unistd::User] from [nix::unistd::Uid], see: {error:#?}")
-        }
-        Ok(potential_user) => {
-            if let Some(user) = potential_user {
-                return user.name;
-            }
+        Err(error) => Err(GameConfigError::LookupCurrentUser(error)),
+        Ok(Some(user)) => Ok(user.name),
+        Ok(None) => Err(GameConfigError::MissingCurrentUser(uid)),
+    }
+}
 
-            panic!(
-                "The [nix::unistd::User] that was received from [nix::unistd::User::from_uid()] is `None`"
-            )
-        }
+pub fn config_directory() -> Result<PathBuf, GameConfigError> {
+    let application_name = env!("CARGO_PKG_NAME");
+    let config_dir_env_var_key = format!("{}_CONFIG_DIR", application_name.to_uppercase());
+
+    if let Ok(dir) = env::var(config_dir_env_var_key) {
+        return Ok(PathBuf::from(dir));
     }
+
+    let linux_username = get_linux_username()?;
+
+    if linux_username == "root" {
+        return Err(GameConfigError::UserIsRoot);
+    }
+
+    Ok(PathBuf::from(&format!(
+        "/home/{}/.config/{}",
+        linux_username, application_name
+    )))
+}
+
+pub fn game_config_directory() -> Result<PathBuf, GameConfigError> {
+    let application_name = env!("CARGO_PKG_NAME");
+    let config_dir_env_var_key = format!("{}_GAME_CONFIG_DIR", application_name.to_uppercase());
+
+    match env::var(config_dir_env_var_key) {
+        Ok(dir) => Ok(PathBuf::from(dir)),
+        Err(_) => Ok(config_directory()?.join("game_configs")),
+    }
+}
+
+pub fn profile_directory() -> Result<PathBuf, GameConfigError> {
+    Ok(config_directory()?.join("profiles"))
 }
 
 #[derive(Debug)]
@@ -29,35 +57,45 @@ pub struct GameConfigFile {
 
 impl GameConfigFile {
     pub async fn from_filename(filename: &str) -> Result<Option<Self>, GameConfigError> {
-        let linux_username = get_linux_username();
-        let application_name = env!("CARGO_PKG_NAME");
-        let config_dir_env_var_key = format!("{}_GAME_CONFIG_DIR", application_name.to_uppercase());
-
-        let config_dir_path = match env::var(config_dir_env_var_key) {
-            Ok(dir) => PathBuf::from(dir),
-            Err(_) => {
-                if linux_username == "root" {
-                    return Err(GameConfigError::UserIsRoot);
-                }
+        Ok(Self::from_path(game_config_directory()?.join(filename)))
+    }
 
-                PathBuf::from(&format!(
-                    "/home/{}/.config/{}/game_configs",
-                    linux_username, application_name
-                ))
-            }
-        };
+    // The `defaults.ron` that every game config is merged on top of.
+    pub async fn defaults() -> Result<Option<Self>, GameConfigError> {
+        match config_directory() {
+            Ok(config_dir_path) => Ok(Self::from_path(config_dir_path.join("defaults"))),
+            // The root user can still use a game config directory set through the environment.
+            Err(GameConfigError::UserIsRoot) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn from_profile_name(
+        profile_dir_path: &Path,
+        profile_name: &str,
+    ) -> Result<Self, GameConfigError> {
+        let profile_path = profile_dir_path.join(format!("{profile_name}.ron"));
 
-        let mut config_file_path = config_dir_path.join(filename);
+        // Profiles are only looked up directly inside the profile directory.
+        if profile_name.contains('/') || profile_name.starts_with('.') {
+            return Err(GameConfigError::MissingProfile(
+                profile_name.to_string(),
+                profile_path,
+            ));
+        }
+
+        Self::from_path(profile_path.clone())
+            .ok_or_else(|| GameConfigError::MissingProfile(profile_name.to_string(), profile_path))
+    }
 
-        config_file_path.set_extension("ron");
+    fn from_path(mut path: PathBuf) -> Option<Self> {
+        path.set_extension("ron");
 
-        if config_file_path.is_file() {
-            return Ok(Some(GameConfigFile {
-                path: config_file_path,
-            }));
+        if path.is_file() {
+            return Some(GameConfigFile { path });
         }
 
-        Ok(None)
+        None
     }
 
     pub async fn read_to_string(&mut self) -> Result<String, GameConfigError> {
@@ -0,0 +1,345 @@
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+const DEFAULT_SEPARATOR: &str = ":";
+
+const EXPECTED_OPERATION: &str =
+    "a string, a boolean, a number, `None`, `(prepend: \"value\")` or `(append: \"value\")`";
+
+// How a layer changes a variable. `Prepend` and `Append` add to the value of the earlier
+// layers, or to the inherited value if no layer set it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnvironmentOperation {
+    Set(String),
+    Unset,
+    // The value and the separator, e.g. `:` for `LD_PRELOAD` or `VK_LAYER_PATH`.
+    Prepend(String, String),
+    Append(String, String),
+}
+
+impl Serialize for EnvironmentOperation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Set(value) => serializer.serialize_str(value),
+            Self::Unset => serializer.serialize_none(),
+            Self::Prepend(value, separator) => {
+                serialize_with_separator(serializer, "prepend", value, separator)
+            }
+            Self::Append(value, separator) => {
+                serialize_with_separator(serializer, "append", value, separator)
+            }
+        }
+    }
+}
+
+fn serialize_with_separator<S: Serializer>(
+    serializer: S,
+    field: &'static str,
+    value: &str,
+    separator: &str,
+) -> Result<S::Ok, S::Error> {
+    let mut operation = serializer.serialize_struct("EnvironmentOperation", 2)?;
+
+    operation.serialize_field(field, value)?;
+    operation.serialize_field("separator", separator)?;
+    operation.end()
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum OperationField {
+    Prepend,
+    Append,
+    Separator,
+}
+
+struct EnvironmentOperationVisitor;
+
+impl<'de> Visitor<'de> for EnvironmentOperationVisitor {
+    type Value = EnvironmentOperation;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(EXPECTED_OPERATION)
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(EnvironmentOperation::Set(u8::from(value).to_string()))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(EnvironmentOperation::Set(value.to_string()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(EnvironmentOperation::Set(value.to_string()))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(EnvironmentOperation::Set(value.to_string()))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(EnvironmentOperation::Set(value.to_string()))
+    }
+
+    // RON passes bare identifiers and `()` alike as a unit without their name, so a typo can't
+    // be told apart from a keyword. `None` is the only value that removes a variable, every unit
+    // is rejected.
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(EnvironmentOperation::Unset)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut prepend = None;
+        let mut append = None;
+        let mut separator = None;
+
+        while let Some(field) = map.next_key::<OperationField>()? {
+            match field {
+                OperationField::Prepend => prepend = Some(map.next_value::<String>()?),
+                OperationField::Append => append = Some(map.next_value::<String>()?),
+                OperationField::Separator => separator = Some(map.next_value::<String>()?),
+            }
+        }
+
+        let separator = separator.unwrap_or_else(|| String::from(DEFAULT_SEPARATOR));
+
+        match (prepend, append) {
+            (Some(value), None) => Ok(EnvironmentOperation::Prepend(value, separator)),
+            (None, Some(value)) => Ok(EnvironmentOperation::Append(value, separator)),
+            (Some(_), Some(_)) => Err(de::Error::custom(
+                "`prepend` and `append` can't be used for the same variable",
+            )),
+            (None, None) => Err(de::Error::missing_field("prepend")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EnvironmentOperation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(EnvironmentOperationVisitor)
+    }
+}
+
+// Either the map form `{ "DXVK_ASYNC": true, "PROTON_LOG": None }` or the older list of
+// `("KEY", "value")` tuples. The operations are kept in order, later ones take precedence.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvironmentVariables(Vec<(String, EnvironmentOperation)>);
+
+impl EnvironmentVariables {
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, EnvironmentOperation)> {
+        self.0.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|(key, _)| key.as_str())
+    }
+}
+
+impl Extend<(String, EnvironmentOperation)> for EnvironmentVariables {
+    fn extend<I: IntoIterator<Item = (String, EnvironmentOperation)>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+impl IntoIterator for EnvironmentVariables {
+    type Item = (String, EnvironmentOperation);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl From<Vec<(String, EnvironmentOperation)>> for EnvironmentVariables {
+    fn from(operations: Vec<(String, EnvironmentOperation)>) -> Self {
+        Self(operations)
+    }
+}
+
+impl Serialize for EnvironmentVariables {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+
+        for (key, operation) in &self.0 {
+            map.serialize_entry(key, operation)?;
+        }
+
+        map.end()
+    }
+}
+
+struct EnvironmentVariablesVisitor;
+
+impl<'de> Visitor<'de> for EnvironmentVariablesVisitor {
+    type Value = EnvironmentVariables;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of environment variables or a list of (key, value) tuples")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut operations = Vec::new();
+
+        while let Some((key, operation)) = map.next_entry::<String, EnvironmentOperation>()? {
+            operations.push((key, operation));
+        }
+
+        Ok(EnvironmentVariables(operations))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut operations = Vec::new();
+
+        while let Some((key, value)) = seq.next_element::<(String, String)>()? {
+            operations.push((key, EnvironmentOperation::Set(value)));
+        }
+
+        Ok(EnvironmentVariables(operations))
+    }
+}
+
+impl<'de> Deserialize<'de> for EnvironmentVariables {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(EnvironmentVariablesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EnvironmentOperation, EnvironmentVariables};
+    use crate::config::GameConfigLayer;
+
+    #[test]
+    fn parse_the_map_and_the_tuple_list_form() {
+        let map_form: EnvironmentVariables = ron::from_str(
+            r#"{
+                "DXVK_ASYNC": true,
+                "DXVK_FRAME_RATE": 60,
+                "PROTON_LOG": None,
+                "MANGOHUD_CONFIG": "fps_limit=60",
+                "LD_PRELOAD": (prepend: "/opt/libfoo.so"),
+                "WINEDLLOVERRIDES": (append: "dxgi=n", separator: ";"),
+            }"#,
+        )
+        .expect("Failed to parse the map form");
+
+        let set = |value: &str| EnvironmentOperation::Set(String::from(value));
+
+        assert_eq!(
+            map_form.iter().cloned().collect::<Vec<_>>(),
+            [
+                (String::from("DXVK_ASYNC"), set("1")),
+                (String::from("DXVK_FRAME_RATE"), set("60")),
+                (String::from("PROTON_LOG"), EnvironmentOperation::Unset),
+                (String::from("MANGOHUD_CONFIG"), set("fps_limit=60")),
+                (
+                    String::from("LD_PRELOAD"),
+                    EnvironmentOperation::Prepend(
+                        String::from("/opt/libfoo.so"),
+                        String::from(":")
+                    )
+                ),
+                (
+                    String::from("WINEDLLOVERRIDES"),
+                    EnvironmentOperation::Append(String::from("dxgi=n"), String::from(";"))
+                ),
+            ]
+        );
+
+        let serialized = ron::to_string(&map_form).expect("Failed to serialize");
+
+        assert_eq!(
+            ron::from_str::<EnvironmentVariables>(&serialized).ok(),
+            Some(map_form)
+        );
+
+        let tuple_list: EnvironmentVariables =
+            ron::from_str(r#"[("DXVK_ASYNC", "1"), ("DXVK_ASYNC", "0")]"#)
+                .expect("Failed to parse the tuple list form");
+
+        assert_eq!(
+            tuple_list.into_iter().collect::<Vec<_>>(),
+            [
+                (String::from("DXVK_ASYNC"), set("1")),
+                (String::from("DXVK_ASYNC"), set("0")),
+            ]
+        );
+
+        assert!(
+            ron::from_str::<EnvironmentVariables>(r#"{"LD_PRELOAD": (prepand: "x")}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn only_remove_a_variable_with_none() {
+        let layer = GameConfigLayer::from_ron_str(
+            r#"(
+                environment_variables: {
+                    "PROTON_LOG": None,
+                    "DXVK_HUD": "None",
+                    "LD_PRELOAD": (prepend: "/opt/libfoo.so"),
+                },
+            )"#,
+        )
+        .expect("Failed to parse the layer");
+
+        assert_eq!(
+            layer
+                .environment_variables
+                .map(|variables| variables.into_iter().map(|(_, operation)| operation))
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>(),
+            [
+                EnvironmentOperation::Unset,
+                EnvironmentOperation::Set(String::from("None")),
+                EnvironmentOperation::Prepend(String::from("/opt/libfoo.so"), String::from(":")),
+            ]
+        );
+
+        // A typo, or any other identifier, must not remove the variable.
+        for value in [
+            "Unset",
+            "Nnoe",
+            "()",
+            r#"Some("x")"#,
+            r#"Some({ "PROTON_LOG": Unset })"#,
+        ] {
+            let source = if value.starts_with("Some({") {
+                format!("(environment_variables: {value})")
+            } else {
+                format!(r#"(environment_variables: {{ "PROTON_LOG": {value} }})"#)
+            };
+
+            let error = GameConfigLayer::from_ron_str(&source)
+                .err()
+                .unwrap_or_else(|| panic!("`{value}` should've been rejected"));
+
+            assert!(
+                error
+                    .code
+                    .to_string()
+                    .to_lowercase()
+                    .contains("expected a string"),
+                "Unexpected error for `{value}`: {error}"
+            );
+        }
+    }
+}
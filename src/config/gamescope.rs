@@ -1,120 +1,680 @@
-use super::ScreenResolution;
+use super::{GamescopeHelp, ResolutionFallback, ScreenResolution};
+use crate::game_launcher::CommandLink;
+use crate::resolution_provider::ResolutionProviderError;
 use crate::UPPERCASE_PACKAGE_NAME;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, env};
 
-const fn _default_start_as_fullscreen() -> bool {
+pub(super) const fn _default_start_as_fullscreen() -> bool {
     true
 }
 
-const fn _default_force_grab_cursor() -> bool {
+pub(super) const fn _default_force_grab_cursor() -> bool {
     true
 }
 
-const fn _default_tearing() -> bool {
+pub(super) const fn _default_tearing() -> bool {
     true
 }
 
-const fn _default_mangoapp() -> bool {
+pub(super) const fn _default_mangoapp() -> bool {
     true
 }
 
-const fn _default_expose_wayland() -> bool {
+pub(super) const fn _default_expose_wayland() -> bool {
     false
 }
 
-#[derive(Deserialize)]
+pub(super) const fn _default_hdr() -> bool {
+    false
+}
+
+pub(super) const fn _default_adaptive_sync() -> bool {
+    false
+}
+
+pub(super) const fn _default_steam() -> bool {
+    false
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct Gamescope {
-    #[serde(default)]
     pub source_resolution: ScreenResolution,
-
-    #[serde(default = "_default_start_as_fullscreen")]
+    // `None` leaves the output resolution up to gamescope.
+    pub output_resolution: Option<ScreenResolution>,
+    pub resolution_fallback: ResolutionFallback,
     pub start_as_fullscreen: bool,
-
-    #[serde(default = "_default_force_grab_cursor")]
     pub force_grab_cursor: bool,
-
-    #[serde(default = "_default_tearing")]
     pub tearing: bool,
-
-    #[serde(default = "_default_mangoapp")]
     pub mangoapp: bool,
+    pub backend: GamescopeBackend,
+    pub expose_wayland: bool,
+    pub upscale_filter: UpscaleFilter,
+    pub upscale_scaler: UpscaleScaler,
+    // From 0 (sharpest) to 20, only used by the Fsr and Nis filters.
+    pub sharpness: Option<u8>,
+    pub hdr: bool,
+    // SDR to HDR inverse tone mapping, `None` disables it.
+    pub hdr_itm: Option<HdrInverseToneMapping>,
+    // From 0 to 1.
+    pub sdr_gamut_wideness: Option<f32>,
+    pub adaptive_sync: bool,
+    // Connectors in order of preference, e.g. `["DP-1", "HDMI-A-1"]`. Only used by the DRM backend.
+    pub prefer_output: Vec<String>,
+    pub default_touch_mode: Option<TouchMode>,
+    // Steam integration, for running gamescope as a Steam Deck like session.
+    pub steam: bool,
+    // The Vulkan device gamescope composites on, as `vendor:device` PCI IDs, e.g. `1002:73bf`.
+    pub prefer_vk_device: Option<String>,
+    // Passed to gamescope as they are, for flags that don't have an option here.
+    pub extra_arguments: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum TouchMode {
+    Hover,
+    Left,
+    Right,
+    Middle,
+    Passthrough,
+}
+
+impl TouchMode {
+    fn as_gamescope_argument(&self) -> &str {
+        match self {
+            Self::Hover => "0",
+            Self::Left => "1",
+            Self::Right => "2",
+            Self::Middle => "3",
+            Self::Passthrough => "4",
+        }
+    }
+}
 
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HdrInverseToneMapping {
+    // The luminance of SDR content used as the input, in nits.
     #[serde(default)]
-    pub backend: GamescopeBackend,
+    pub sdr_nits: Option<u32>,
 
-    #[serde(default = "_default_expose_wayland")]
-    pub expose_wayland: bool,
+    #[serde(default)]
+    pub target_nits: Option<u32>,
 }
 
 impl Default for Gamescope {
     fn default() -> Self {
         Self {
             source_resolution: ScreenResolution::default(),
+            output_resolution: None,
+            resolution_fallback: ResolutionFallback::default(),
             start_as_fullscreen: _default_start_as_fullscreen(),
             force_grab_cursor: _default_force_grab_cursor(),
             tearing: _default_tearing(),
             mangoapp: _default_mangoapp(),
             backend: GamescopeBackend::default(),
             expose_wayland: _default_expose_wayland(),
+            upscale_filter: UpscaleFilter::default(),
+            upscale_scaler: UpscaleScaler::default(),
+            sharpness: None,
+            hdr: _default_hdr(),
+            hdr_itm: None,
+            sdr_gamut_wideness: None,
+            adaptive_sync: _default_adaptive_sync(),
+            prefer_output: Vec::new(),
+            default_touch_mode: None,
+            steam: _default_steam(),
+            prefer_vk_device: None,
+            extra_arguments: Vec::new(),
         }
     }
 }
 
-#[derive(Deserialize, Default, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
 pub enum GamescopeBackend {
     #[default]
     Auto,
     Wayland,
+    Sdl,
+    // Runs without a desktop session, e.g. from a TTY.
+    Drm,
+    // Outputs as a VR overlay.
+    Openvr,
+    // No window and no output, e.g. for benchmarks.
+    Headless,
 }
 
 impl GamescopeBackend {
-    fn as_gamescope_command_argument(&self) -> String {
+    pub fn supports_hdr(&self) -> bool {
+        match self {
+            Self::Auto | Self::Wayland | Self::Drm => true,
+            Self::Sdl | Self::Openvr | Self::Headless => false,
+        }
+    }
+
+    fn as_gamescope_command_arguments(&self) -> [&str; 2] {
         let argument_var = match self {
             Self::Auto => "auto",
             Self::Wayland => "wayland",
+            Self::Sdl => "sdl",
+            Self::Drm => "drm",
+            Self::Openvr => "openvr",
+            Self::Headless => "headless",
         };
 
-        format!("--backend {argument_var}")
+        ["--backend", argument_var]
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub enum UpscaleFilter {
+    #[default]
+    Linear,
+    Nearest,
+    Fsr,
+    Nis,
+    Pixel,
+}
+
+impl UpscaleFilter {
+    fn as_gamescope_argument(&self) -> &str {
+        match self {
+            Self::Linear => "linear",
+            Self::Nearest => "nearest",
+            Self::Fsr => "fsr",
+            Self::Nis => "nis",
+            Self::Pixel => "pixel",
+        }
+    }
+
+    // The flags gamescope used before `-F` was added, `None` if there weren't any.
+    fn as_legacy_gamescope_arguments(&self) -> Option<&[&str]> {
+        match self {
+            Self::Linear => Some(&[]),
+            Self::Nearest => Some(&["-n"]),
+            Self::Fsr => Some(&["-U"]),
+            Self::Nis => Some(&["-Y"]),
+            Self::Pixel => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub enum UpscaleScaler {
+    #[default]
+    Auto,
+    Integer,
+    Fit,
+    Fill,
+    Stretch,
+}
+
+impl UpscaleScaler {
+    fn as_gamescope_argument(&self) -> &str {
+        match self {
+            Self::Auto => "auto",
+            Self::Integer => "integer",
+            Self::Fit => "fit",
+            Self::Fill => "fill",
+            Self::Stretch => "stretch",
+        }
+    }
+
+    // The flags gamescope used before `-S` was added, `None` if there weren't any.
+    fn as_legacy_gamescope_arguments(&self) -> Option<&[&str]> {
+        match self {
+            Self::Auto | Self::Fit => Some(&[]),
+            Self::Integer => Some(&["-i"]),
+            Self::Fill | Self::Stretch => None,
+        }
     }
 }
 
 impl Gamescope {
-    pub fn as_command(&self, gamescope_executable_path: &str) -> String {
-        let mut arguments: Vec<&str> = Vec::new();
+    // The variables games need to output HDR through gamescope.
+    pub fn environment_variables(&self) -> Vec<(String, String)> {
+        if !self.hdr {
+            return Vec::new();
+        }
+
+        vec![
+            (String::from("ENABLE_HDR_WSI"), String::from("1")),
+            (String::from("DXVK_HDR"), String::from("1")),
+        ]
+    }
 
-        let screen_resolution_as_argument = self.source_resolution.as_gamescope_command_argument();
+    // Why gamescope won't be able to output HDR in the current session, if it's enabled.
+    pub fn hdr_unsupported_reason(&self) -> Option<String> {
+        self.hdr_unsupported_reason_with(|variable| {
+            env::var(variable).ok().filter(|value| !value.is_empty())
+        })
+    }
+
+    fn hdr_unsupported_reason_with(
+        &self,
+        get_env: impl Fn(&str) -> Option<String>,
+    ) -> Option<String> {
+        if !self.hdr {
+            return None;
+        }
+
+        if !self.backend.supports_hdr() {
+            return Some(format!(
+                "gamescope's {:?} backend can't output HDR",
+                self.backend
+            ));
+        }
+
+        // Without a display server gamescope runs on DRM directly, which can always do HDR.
+        if get_env("WAYLAND_DISPLAY").is_none() {
+            return get_env("DISPLAY").map(|_| {
+                String::from("gamescope can't output HDR when it's nested in an X11 session")
+            });
+        }
+
+        let current_desktop = get_env("XDG_CURRENT_DESKTOP").unwrap_or_default();
+
+        let supports_hdr = current_desktop
+            .to_lowercase()
+            .split(':')
+            .any(|desktop| ["kde", "gnome", "hyprland"].contains(&desktop));
+
+        if supports_hdr {
+            return None;
+        }
+
+        if current_desktop.is_empty() {
+            return Some(String::from(
+                "The compositor couldn't be detected, it might not be able to display HDR",
+            ));
+        }
+
+        Some(format!(
+            "The compositor `{current_desktop}` doesn't support HDR, gamescope's output will be tone mapped to SDR"
+        ))
+    }
+
+    fn hdr_arguments(&self) -> Vec<String> {
+        let mut arguments: Vec<String> = Vec::new();
+
+        if self.hdr {
+            arguments.push(String::from("--hdr-enabled"));
+        }
 
-        arguments.push(&screen_resolution_as_argument);
+        if let Some(hdr_itm) = &self.hdr_itm {
+            arguments.push(String::from("--hdr-itm-enable"));
+
+            if let Some(sdr_nits) = hdr_itm.sdr_nits {
+                arguments.push(String::from("--hdr-itm-sdr-nits"));
+                arguments.push(sdr_nits.to_string());
+            }
+
+            if let Some(target_nits) = hdr_itm.target_nits {
+                arguments.push(String::from("--hdr-itm-target-nits"));
+                arguments.push(target_nits.to_string());
+            }
+        }
+
+        if let Some(sdr_gamut_wideness) = self.sdr_gamut_wideness {
+            arguments.push(String::from("--sdr-gamut-wideness"));
+            arguments.push(sdr_gamut_wideness.to_string());
+        }
+
+        arguments
+    }
+
+    fn upscaler_arguments(&self, help: &GamescopeHelp) -> Vec<String> {
+        let mut arguments: Vec<String> = Vec::new();
+
+        if help.uses_legacy_upscaler_flags() {
+            match self.upscale_filter.as_legacy_gamescope_arguments() {
+                Some(legacy_arguments) => {
+                    arguments.extend(legacy_arguments.iter().copied().map(String::from))
+                }
+                None => tracing::warn!(
+                    "The installed gamescope doesn't support the {:?} upscale filter, ignoring it.",
+                    self.upscale_filter
+                ),
+            }
+
+            match self.upscale_scaler.as_legacy_gamescope_arguments() {
+                Some(legacy_arguments) => {
+                    arguments.extend(legacy_arguments.iter().copied().map(String::from))
+                }
+                None => tracing::warn!(
+                    "The installed gamescope doesn't support the {:?} upscale scaler, ignoring it.",
+                    self.upscale_scaler
+                ),
+            }
+
+            if let Some(sharpness) = self.sharpness {
+                arguments.push(String::from("--fsr-sharpness"));
+                arguments.push(sharpness.to_string());
+            }
+
+            return arguments;
+        }
+
+        if self.upscale_filter != UpscaleFilter::Linear {
+            arguments.push(String::from("-F"));
+            arguments.push(String::from(self.upscale_filter.as_gamescope_argument()));
+        }
+
+        if self.upscale_scaler != UpscaleScaler::Auto {
+            arguments.push(String::from("-S"));
+            arguments.push(String::from(self.upscale_scaler.as_gamescope_argument()));
+        }
+
+        if let Some(sharpness) = self.sharpness {
+            arguments.push(String::from("--sharpness"));
+            arguments.push(sharpness.to_string());
+        }
+
+        arguments
+    }
+
+    pub fn as_command(
+        &self,
+        gamescope_executable_path: &str,
+        help: &GamescopeHelp,
+    ) -> Result<CommandLink, ResolutionProviderError> {
+        let mut arguments: Vec<String> = Vec::new();
+
+        let output_monitor = match &self.output_resolution {
+            Some(output_resolution) => {
+                output_resolution.resolve(None, &self.resolution_fallback)?
+            }
+            None => None,
+        };
+
+        let source_monitor = self
+            .source_resolution
+            .resolve(output_monitor.as_ref(), &self.resolution_fallback)?;
+
+        if let Some(source_monitor) = &source_monitor {
+            arguments.extend([
+                String::from("-w"),
+                source_monitor.width.to_string(),
+                String::from("-h"),
+                source_monitor.height.to_string(),
+            ]);
+        }
+
+        if let Some(output_monitor) = &output_monitor {
+            arguments.extend([
+                String::from("-W"),
+                output_monitor.width.to_string(),
+                String::from("-H"),
+                output_monitor.height.to_string(),
+            ]);
+        }
+
+        let refresh_rate = [&source_monitor, &output_monitor]
+            .into_iter()
+            .find_map(|monitor| monitor.as_ref()?.refresh_rate);
+
+        if let Some(refresh_rate) = refresh_rate {
+            arguments.push(String::from("-r"));
+            arguments.push((refresh_rate.round() as u32).to_string());
+        }
+
+        arguments.extend(self.upscaler_arguments(help));
 
         if self.start_as_fullscreen {
-            arguments.push("--fullscreen")
+            arguments.push(String::from("--fullscreen"))
         }
 
         if self.force_grab_cursor {
-            arguments.push("--force-grab-cursor")
+            arguments.push(String::from("--force-grab-cursor"))
         }
 
         if self.tearing {
-            arguments.push("--immediate-flips")
+            arguments.push(String::from("--immediate-flips"))
         }
 
         if self.mangoapp {
-            arguments.push("--mangoapp")
+            arguments.push(String::from("--mangoapp"))
         }
 
-        let backend_as_argument = self.backend.as_gamescope_command_argument();
-
         if self.backend != GamescopeBackend::Auto {
-            arguments.push(&backend_as_argument);
+            arguments.extend(
+                self.backend
+                    .as_gamescope_command_arguments()
+                    .map(String::from),
+            );
         }
 
         if self.expose_wayland {
-            arguments.push("--expose-wayland")
+            arguments.push(String::from("--expose-wayland"))
         }
 
-        let arguments_as_string = arguments.join(" ");
+        arguments.extend(self.hdr_arguments());
+
+        if self.adaptive_sync {
+            arguments.push(String::from("--adaptive-sync"));
+        }
+
+        if !self.prefer_output.is_empty() {
+            arguments.push(String::from("--prefer-output"));
+            arguments.push(self.prefer_output.join(","));
+        }
+
+        if let Some(default_touch_mode) = &self.default_touch_mode {
+            arguments.push(String::from("--default-touch-mode"));
+            arguments.push(String::from(default_touch_mode.as_gamescope_argument()));
+        }
+
+        if self.steam {
+            arguments.push(String::from("--steam"));
+        }
+
+        if let Some(prefer_vk_device) = &self.prefer_vk_device {
+            arguments.push(String::from("--prefer-vk-device"));
+            arguments.push(prefer_vk_device.clone());
+        }
+
+        arguments.extend(self.extra_arguments.iter().cloned());
+
+        // Everything after this belongs to the game, not to gamescope.
+        arguments.push(String::from("--"));
+
+        Ok(CommandLink::with_arguments(
+            gamescope_executable_path,
+            arguments,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Gamescope, GamescopeBackend, HdrInverseToneMapping, TouchMode, UpscaleFilter, UpscaleScaler,
+    };
+    use crate::config::{GamescopeHelp, ScreenResolution};
+
+    fn gamescope_arguments(gamescope: Gamescope) -> Vec<String> {
+        gamescope
+            .as_command("gamescope", &GamescopeHelp::default())
+            .expect("Failed to create the gamescope command")
+            .arguments
+    }
+
+    #[test]
+    fn map_source_and_output_resolutions() {
+        let arguments = gamescope_arguments(Gamescope {
+            source_resolution: ScreenResolution::Custom(1920, 1080),
+            output_resolution: Some(ScreenResolution::Custom(3840, 2160)),
+            start_as_fullscreen: false,
+            force_grab_cursor: false,
+            tearing: false,
+            mangoapp: false,
+            ..Gamescope::default()
+        });
+
+        assert_eq!(
+            arguments,
+            ["-w", "1920", "-h", "1080", "-W", "3840", "-H", "2160", "--"]
+        );
+    }
+
+    #[test]
+    fn scale_the_source_resolution_from_the_output_resolution() {
+        let arguments = gamescope_arguments(Gamescope {
+            source_resolution: ScreenResolution::Scale(0.67),
+            output_resolution: Some(ScreenResolution::Custom(2560, 1440)),
+            ..Gamescope::default()
+        });
+
+        assert_eq!(
+            arguments[..8],
+            ["-w", "1715", "-h", "965", "-W", "2560", "-H", "1440"]
+        );
+        assert_eq!(arguments.last().map(String::as_str), Some("--"));
+    }
+
+    #[test]
+    fn map_upscaler_to_the_installed_gamescope_flags() {
+        let gamescope = Gamescope {
+            source_resolution: ScreenResolution::Custom(1920, 1080),
+            start_as_fullscreen: false,
+            force_grab_cursor: false,
+            tearing: false,
+            mangoapp: false,
+            upscale_filter: UpscaleFilter::Fsr,
+            upscale_scaler: UpscaleScaler::Integer,
+            sharpness: Some(5),
+            ..Gamescope::default()
+        };
+
+        let legacy_help = GamescopeHelp::from_help_text(
+            include_str!("../../tests/fixtures/gamescope_help/3.11.52.txt").to_string(),
+            None,
+        );
+
+        assert_eq!(
+            gamescope.upscaler_arguments(&legacy_help),
+            ["-U", "-i", "--fsr-sharpness", "5"]
+        );
+        assert_eq!(
+            gamescope_arguments(gamescope),
+            [
+                "-w",
+                "1920",
+                "-h",
+                "1080",
+                "-F",
+                "fsr",
+                "-S",
+                "integer",
+                "--sharpness",
+                "5",
+                "--"
+            ]
+        );
+    }
+
+    #[test]
+    fn enable_hdr_and_warn_about_sessions_without_hdr() {
+        let gamescope = Gamescope {
+            source_resolution: ScreenResolution::Custom(3840, 2160),
+            start_as_fullscreen: false,
+            force_grab_cursor: false,
+            tearing: false,
+            mangoapp: false,
+            hdr: true,
+            hdr_itm: Some(HdrInverseToneMapping {
+                sdr_nits: None,
+                target_nits: Some(1000),
+            }),
+            adaptive_sync: true,
+            ..Gamescope::default()
+        };
+
+        let session = |variables: &'static [(&'static str, &'static str)]| {
+            move |variable: &str| {
+                variables
+                    .iter()
+                    .find(|(key, _)| *key == variable)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            gamescope.hdr_unsupported_reason_with(session(&[
+                ("WAYLAND_DISPLAY", "wayland-0"),
+                ("XDG_CURRENT_DESKTOP", "KDE"),
+            ])),
+            None
+        );
+        assert!(gamescope
+            .hdr_unsupported_reason_with(session(&[
+                ("WAYLAND_DISPLAY", "wayland-1"),
+                ("XDG_CURRENT_DESKTOP", "sway"),
+            ]))
+            .is_some());
+        assert!(gamescope
+            .hdr_unsupported_reason_with(session(&[("DISPLAY", ":0")]))
+            .is_some());
+        assert_eq!(gamescope.hdr_unsupported_reason_with(session(&[])), None);
+
+        assert_eq!(
+            gamescope.environment_variables(),
+            [
+                (String::from("ENABLE_HDR_WSI"), String::from("1")),
+                (String::from("DXVK_HDR"), String::from("1")),
+            ]
+        );
+        assert_eq!(
+            gamescope_arguments(gamescope),
+            [
+                "-w",
+                "3840",
+                "-h",
+                "2160",
+                "--hdr-enabled",
+                "--hdr-itm-enable",
+                "--hdr-itm-target-nits",
+                "1000",
+                "--adaptive-sync",
+                "--"
+            ]
+        );
+    }
+
+    #[test]
+    fn map_drm_backend_options() {
+        let arguments = gamescope_arguments(Gamescope {
+            source_resolution: ScreenResolution::Custom(1280, 800),
+            start_as_fullscreen: false,
+            force_grab_cursor: false,
+            tearing: false,
+            mangoapp: false,
+            backend: GamescopeBackend::Drm,
+            prefer_output: vec![String::from("eDP-1"), String::from("DP-1")],
+            default_touch_mode: Some(TouchMode::Passthrough),
+            steam: true,
+            prefer_vk_device: Some(String::from("1002:163f")),
+            ..Gamescope::default()
+        });
 
-        format!("{gamescope_executable_path} {arguments_as_string}")
+        assert_eq!(
+            arguments,
+            [
+                "-w",
+                "1280",
+                "-h",
+                "800",
+                "--backend",
+                "drm",
+                "--prefer-output",
+                "eDP-1,DP-1",
+                "--default-touch-mode",
+                "4",
+                "--steam",
+                "--prefer-vk-device",
+                "1002:163f",
+                "--"
+            ]
+        );
     }
 }
@@ -0,0 +1,269 @@
+use crate::RUNTIME_DIRECTORY;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tokio::{fs, process::Command};
+use which::which;
+
+lazy_static! {
+    static ref GAMESCOPE_VERSION: Regex = Regex::new(r"gamescope version (\S+)").unwrap();
+}
+
+// What the installed gamescope supports, based on the output of `gamescope --help`. The flags
+// change between versions, e.g. `-U`/`-Y` were replaced with `-F fsr`/`-F nis` in 3.12.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq)]
+pub struct GamescopeHelp {
+    // `None` if gamescope couldn't be probed, every flag is assumed to be supported then.
+    help_text: Option<String>,
+    version: Option<String>,
+}
+
+// Probing gamescope takes a moment, so the result is kept in the runtime directory until the
+// executable changes.
+#[derive(Deserialize, Serialize)]
+struct CachedGamescopeHelp {
+    executable_path: PathBuf,
+    modified: SystemTime,
+    help: GamescopeHelp,
+}
+
+impl GamescopeHelp {
+    pub fn from_help_text(help_text: String, version: Option<String>) -> Self {
+        Self {
+            help_text: Some(help_text),
+            version,
+        }
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    fn cache_path() -> PathBuf {
+        RUNTIME_DIRECTORY.join("gamescope-help.json")
+    }
+
+    pub async fn probe(gamescope_executable_path: &str) -> Self {
+        Self::probe_cached_at(gamescope_executable_path, &Self::cache_path()).await
+    }
+
+    async fn probe_cached_at(gamescope_executable_path: &str, cache_path: &Path) -> Self {
+        let executable = which(gamescope_executable_path)
+            .ok()
+            .and_then(|executable_path| {
+                let modified = std::fs::metadata(&executable_path).ok()?.modified().ok()?;
+
+                Some((executable_path, modified))
+            });
+
+        if let Some((executable_path, modified)) = &executable {
+            if let Ok(cache) = fs::read(cache_path).await {
+                match serde_json::from_slice::<CachedGamescopeHelp>(&cache) {
+                    Ok(cached)
+                        if cached.executable_path == *executable_path
+                            && cached.modified == *modified =>
+                    {
+                        return cached.help;
+                    }
+                    Ok(_) => {}
+                    Err(error) => {
+                        tracing::debug!("Ignoring the invalid gamescope help cache. {error}")
+                    }
+                }
+            }
+        }
+
+        let help = Self::probe_uncached(gamescope_executable_path).await;
+
+        if let (Some((executable_path, modified)), Some(_)) = (executable, &help.help_text) {
+            let cached = CachedGamescopeHelp {
+                executable_path,
+                modified,
+                help,
+            };
+
+            if let Err(error) = Self::write_cache(&cached, cache_path).await {
+                tracing::debug!("Failed to cache gamescope's help. {error}");
+            }
+
+            return cached.help;
+        }
+
+        help
+    }
+
+    async fn write_cache(cached: &CachedGamescopeHelp, cache_path: &Path) -> std::io::Result<()> {
+        if let Some(cache_directory) = cache_path.parent() {
+            fs::create_dir_all(cache_directory).await?;
+        }
+
+        fs::write(cache_path, serde_json::to_vec(cached)?).await
+    }
+
+    async fn run(gamescope_executable_path: &str, argument: &str) -> std::io::Result<String> {
+        let output = Command::new(gamescope_executable_path)
+            .arg(argument)
+            .output()
+            .await?;
+
+        // gamescope prints its help to stderr.
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+
+        Ok(text)
+    }
+
+    async fn probe_uncached(gamescope_executable_path: &str) -> Self {
+        match Self::run(gamescope_executable_path, "--help").await {
+            Ok(help_text) if help_text.contains("usage: gamescope") => {
+                let version = Self::run(gamescope_executable_path, "--version")
+                    .await
+                    .ok()
+                    .and_then(|version_text| {
+                        Some(GAMESCOPE_VERSION.captures(&version_text)?[1].to_string())
+                    });
+
+                return Self::from_help_text(help_text, version);
+            }
+            Ok(_) => {
+                tracing::warn!("`{gamescope_executable_path} --help` didn't print gamescope's usage, assuming it supports every flag.");
+            }
+            Err(error) => {
+                tracing::warn!("Failed to run `{gamescope_executable_path} --help`, assuming it supports every flag. {error}");
+            }
+        }
+
+        Self::default()
+    }
+
+    pub fn supports_flag(&self, flag: &str) -> bool {
+        let Some(help_text) = &self.help_text else {
+            return true;
+        };
+
+        // Flags are separated by whitespace or `, `, e.g. `  -F, --filter   upscaler filter`.
+        help_text.lines().any(|line| {
+            line.split(|character: char| character.is_whitespace() || character == ',')
+                .take_while(|word| word.is_empty() || word.starts_with('-'))
+                .any(|word| word == flag)
+        })
+    }
+
+    // The first flag in the arguments gamescope doesn't know, e.g. `--foo` in `--foo=bar`.
+    pub fn find_unsupported_flag<'a>(&self, arguments: &'a [String]) -> Option<&'a str> {
+        arguments
+            .iter()
+            .take_while(|argument| *argument != "--")
+            .filter(|argument| argument.starts_with('-'))
+            .map(|argument| argument.split('=').next().unwrap_or(argument))
+            .find(|flag| !self.supports_flag(flag))
+    }
+
+    // Before 3.12 upscaling was configured with `-U`, `-Y`, `-n` and `-i` instead of `-F`/`-S`.
+    pub fn uses_legacy_upscaler_flags(&self) -> bool {
+        !self.supports_flag("--filter") && self.supports_flag("--fsr-upscaling")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GamescopeHelp;
+    use color_eyre::eyre;
+    use std::{
+        fs::{self, File},
+        os::unix::fs::PermissionsExt,
+        time::{Duration, SystemTime},
+    };
+    use tempdir::TempDir;
+
+    #[test]
+    fn detect_the_upscaler_flag_syntax() {
+        let legacy = GamescopeHelp::from_help_text(
+            include_str!("../../tests/fixtures/gamescope_help/3.11.52.txt").to_string(),
+            Some(String::from("3.11.52")),
+        );
+        let current = GamescopeHelp::from_help_text(
+            include_str!("../../tests/fixtures/gamescope_help/3.14.2.txt").to_string(),
+            Some(String::from("3.14.2")),
+        );
+
+        assert!(legacy.uses_legacy_upscaler_flags());
+        assert!(legacy.supports_flag("-U"));
+        assert!(!legacy.supports_flag("--sharpness"));
+
+        assert!(!current.uses_legacy_upscaler_flags());
+        assert!(current.supports_flag("-F"));
+        assert!(current.supports_flag("--fsr-sharpness"));
+        assert!(!current.supports_flag("--fsr-upscaling"));
+
+        assert!(!GamescopeHelp::default().uses_legacy_upscaler_flags());
+
+        let arguments: Vec<String> = [
+            "-w",
+            "1280",
+            "--mangoapp",
+            "--rt",
+            "--hdr-debug-force-output=1",
+            "--",
+            "--wat",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        assert_eq!(current.find_unsupported_flag(&arguments), None);
+        assert_eq!(legacy.find_unsupported_flag(&arguments), Some("--mangoapp"));
+        assert_eq!(
+            GamescopeHelp::default().find_unsupported_flag(&arguments),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn cache_the_help_until_the_executable_changes() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("cache_the_help_until_the_executable_changes")?;
+        let gamescope = temp_dir.path().join("gamescope");
+        let runs = temp_dir.path().join("runs");
+        let cache = temp_dir.path().join("runtime/gamescope-help.json");
+
+        // Counts how often it's run.
+        fs::write(
+            &gamescope,
+            format!(
+                "#!/bin/sh\necho \"$1\" >> '{}'\necho 'usage: gamescope [options...] -- [app]'\necho 'gamescope version 3.14.2'\n",
+                runs.display()
+            ),
+        )?;
+        fs::set_permissions(&gamescope, fs::Permissions::from_mode(0o755))?;
+
+        let gamescope_path = gamescope.to_string_lossy();
+        let probed = GamescopeHelp::probe_cached_at(&gamescope_path, &cache).await;
+
+        assert_eq!(probed.version(), Some("3.14.2"));
+        assert!(cache.is_file());
+        assert_eq!(fs::read_to_string(&runs)?.lines().count(), 2);
+
+        assert_eq!(
+            GamescopeHelp::probe_cached_at(&gamescope_path, &cache).await,
+            probed
+        );
+        assert_eq!(fs::read_to_string(&runs)?.lines().count(), 2);
+
+        File::options()
+            .write(true)
+            .open(&gamescope)?
+            .set_modified(SystemTime::now() + Duration::from_secs(60))?;
+
+        assert_eq!(
+            GamescopeHelp::probe_cached_at(&gamescope_path, &cache).await,
+            probed
+        );
+        assert_eq!(fs::read_to_string(&runs)?.lines().count(), 4);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+const SYSFS_DIRECTORY: &str = "/sys";
+
+// PCI class 0x03 covers VGA, 3D and other display controllers.
+const PCI_DISPLAY_CONTROLLER_CLASS: &str = "0x03";
+
+#[derive(Debug, thiserror::Error)]
+pub enum GpuError {
+    #[error("IO error while attempting to read the GPUs from `{1:#?}`, see: {0:#?}")]
+    ReadSysfs(io::Error, PathBuf),
+
+    #[error("Couldn't find any GPUs with a DRM card in `/sys/class/drm`")]
+    NoGpus,
+
+    #[error("There's no GPU with the PCI address `{0}`, the available GPUs are: {}", .1.join(", "))]
+    MissingPciAddress(String, Vec<String>),
+
+    #[error("There's no {0:?} GPU, the available GPUs are: {}", .1.join(", "))]
+    MissingVendor(GpuVendor, Vec<String>),
+
+    #[error("There's no GPU with the index {0}, the available GPUs are: {}", .1.join(", "))]
+    MissingIndex(usize, Vec<String>),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum GpuVendor {
+    Amd,
+    Intel,
+    Nvidia,
+}
+
+impl GpuVendor {
+    fn from_pci_vendor_id(vendor_id: u16) -> Option<Self> {
+        match vendor_id {
+            0x1002 => Some(Self::Amd),
+            0x8086 => Some(Self::Intel),
+            0x10de => Some(Self::Nvidia),
+            _ => None,
+        }
+    }
+}
+
+// Which GPU the game should render on.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum GpuSelector {
+    // e.g. `PciAddress("0000:03:00.0")`, the leading domain can be left out.
+    PciAddress(String),
+    // Discrete GPUs are preferred if there are multiple GPUs of the vendor.
+    Vendor(GpuVendor),
+    // The position in the list of GPUs sorted by PCI address, starting at 0.
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gpu {
+    pub pci_address: String,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub driver: Option<String>,
+    // Whether the firmware initialized the GPU, usually the integrated GPU on laptops.
+    pub boot_vga: bool,
+}
+
+impl fmt::Display for Gpu {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{} ({:04x}:{:04x}, {})",
+            self.pci_address,
+            self.vendor_id,
+            self.device_id,
+            self.driver.as_deref().unwrap_or("no driver")
+        )
+    }
+}
+
+impl Gpu {
+    pub fn vendor(&self) -> Option<GpuVendor> {
+        GpuVendor::from_pci_vendor_id(self.vendor_id)
+    }
+
+    // `vendor:device`, the format of gamescope's `--prefer-vk-device` and `MESA_VK_DEVICE_SELECT`.
+    pub fn pci_id(&self) -> String {
+        format!("{:04x}:{:04x}", self.vendor_id, self.device_id)
+    }
+
+    // The variables that make Vulkan and OpenGL pick this GPU.
+    pub fn environment_variables(&self) -> Vec<(String, String)> {
+        if self.driver.as_deref() == Some("nvidia") {
+            return vec![
+                (String::from("__NV_PRIME_RENDER_OFFLOAD"), String::from("1")),
+                (
+                    String::from("__GLX_VENDOR_LIBRARY_NAME"),
+                    String::from("nvidia"),
+                ),
+                (
+                    String::from("__VK_LAYER_NV_optimus"),
+                    String::from("NVIDIA_only"),
+                ),
+            ];
+        }
+
+        // Mesa drivers, `DRI_PRIME` takes the PCI address with `_` instead of `:` and `.`.
+        vec![
+            (
+                String::from("DRI_PRIME"),
+                format!("pci-{}", self.pci_address.replace([':', '.'], "_")),
+            ),
+            (String::from("MESA_VK_DEVICE_SELECT"), self.pci_id()),
+        ]
+    }
+}
+
+fn read_attribute(path: &Path) -> Result<Option<String>, GpuError> {
+    match fs::read_to_string(path) {
+        Ok(value) => Ok(Some(value.trim().to_string())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(GpuError::ReadSysfs(error, path.to_path_buf())),
+    }
+}
+
+fn read_uevent(path: &Path) -> Result<HashMap<String, String>, GpuError> {
+    Ok(read_attribute(path)?
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect())
+}
+
+fn read_dir_names(path: &Path) -> Result<Vec<String>, GpuError> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(GpuError::ReadSysfs(error, path.to_path_buf())),
+    };
+
+    let mut names = entries
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+        .collect::<io::Result<Vec<String>>>()
+        .map_err(|error| GpuError::ReadSysfs(error, path.to_path_buf()))?;
+
+    names.sort();
+
+    Ok(names)
+}
+
+fn parse_pci_id(id: Option<String>) -> u16 {
+    id.and_then(|id| u16::from_str_radix(id.trim_start_matches("0x"), 16).ok())
+        .unwrap_or_default()
+}
+
+// The display controllers on the PCI bus that have a DRM card, the ones that are bound to
+// e.g. vfio-pci for a VM can't be used. Sorted by PCI address.
+pub fn enumerate_gpus(sysfs_directory: &Path) -> Result<Vec<Gpu>, GpuError> {
+    let drm_directory = sysfs_directory.join("class/drm");
+    let mut drm_pci_addresses = Vec::new();
+
+    // Connectors are named like `card1-DP-1`, render nodes `renderD128`.
+    for card_name in read_dir_names(&drm_directory)? {
+        if !card_name.starts_with("card") || card_name.contains('-') {
+            continue;
+        }
+
+        let uevent = read_uevent(&drm_directory.join(&card_name).join("device/uevent"))?;
+
+        if let Some(pci_address) = uevent.get("PCI_SLOT_NAME") {
+            drm_pci_addresses.push(pci_address.clone());
+        }
+    }
+
+    let pci_directory = sysfs_directory.join("bus/pci/devices");
+    let mut gpus = Vec::new();
+
+    for pci_address in read_dir_names(&pci_directory)? {
+        let device_directory = pci_directory.join(&pci_address);
+
+        let is_display_controller = read_attribute(&device_directory.join("class"))?
+            .is_some_and(|class| class.starts_with(PCI_DISPLAY_CONTROLLER_CLASS));
+
+        if !is_display_controller || !drm_pci_addresses.contains(&pci_address) {
+            continue;
+        }
+
+        gpus.push(Gpu {
+            vendor_id: parse_pci_id(read_attribute(&device_directory.join("vendor"))?),
+            device_id: parse_pci_id(read_attribute(&device_directory.join("device"))?),
+            driver: read_uevent(&device_directory.join("uevent"))?.remove("DRIVER"),
+            boot_vga: read_attribute(&device_directory.join("boot_vga"))?.as_deref() == Some("1"),
+            pci_address,
+        });
+    }
+
+    Ok(gpus)
+}
+
+pub fn installed_gpus() -> Result<Vec<Gpu>, GpuError> {
+    enumerate_gpus(Path::new(SYSFS_DIRECTORY))
+}
+
+impl GpuSelector {
+    pub fn select(&self) -> Result<Gpu, GpuError> {
+        self.select_from(installed_gpus()?)
+    }
+
+    fn select_from(&self, mut gpus: Vec<Gpu>) -> Result<Gpu, GpuError> {
+        if gpus.is_empty() {
+            return Err(GpuError::NoGpus);
+        }
+
+        let available_gpus = || gpus.iter().map(Gpu::to_string).collect::<Vec<String>>();
+
+        let index = match self {
+            Self::PciAddress(pci_address) => gpus
+                .iter()
+                .position(|gpu| {
+                    // Without the `dddd:` domain, e.g. `03:00.0`.
+                    gpu.pci_address.eq_ignore_ascii_case(pci_address)
+                        || gpu
+                            .pci_address
+                            .split_once(':')
+                            .is_some_and(|(_, address)| address.eq_ignore_ascii_case(pci_address))
+                })
+                .ok_or_else(|| GpuError::MissingPciAddress(pci_address.clone(), available_gpus())),
+            Self::Vendor(vendor) => {
+                let is_vendor = |gpu: &&Gpu| gpu.vendor() == Some(*vendor);
+
+                gpus.iter()
+                    .filter(is_vendor)
+                    .find(|gpu| !gpu.boot_vga)
+                    .or_else(|| gpus.iter().find(is_vendor))
+                    .and_then(|selected_gpu| gpus.iter().position(|gpu| gpu == selected_gpu))
+                    .ok_or_else(|| GpuError::MissingVendor(*vendor, available_gpus()))
+            }
+            Self::Index(index) if *index < gpus.len() => Ok(*index),
+            Self::Index(index) => Err(GpuError::MissingIndex(*index, available_gpus())),
+        }?;
+
+        Ok(gpus.swap_remove(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{enumerate_gpus, Gpu, GpuSelector, GpuVendor};
+    use std::path::PathBuf;
+
+    #[test]
+    fn enumerate_and_select_gpus() {
+        let gpus = enumerate_gpus(
+            &PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/gpu_sysfs"),
+        )
+        .expect("Failed to read the fixture");
+
+        assert_eq!(
+            gpus.iter()
+                .map(|gpu| (gpu.pci_address.as_str(), gpu.pci_id(), gpu.vendor()))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "0000:00:02.0",
+                    String::from("8086:a780"),
+                    Some(GpuVendor::Intel)
+                ),
+                (
+                    "0000:01:00.0",
+                    String::from("10de:2684"),
+                    Some(GpuVendor::Nvidia)
+                ),
+                (
+                    "0000:03:00.0",
+                    String::from("1002:73bf"),
+                    Some(GpuVendor::Amd)
+                ),
+            ]
+        );
+
+        let select = |selector: GpuSelector| selector.select_from(gpus.clone());
+
+        let amd_gpu = select(GpuSelector::Vendor(GpuVendor::Amd)).expect("Failed to select");
+
+        assert_eq!(amd_gpu.pci_address, "0000:03:00.0");
+        assert_eq!(
+            amd_gpu.environment_variables(),
+            [
+                (String::from("DRI_PRIME"), String::from("pci-0000_03_00_0")),
+                (
+                    String::from("MESA_VK_DEVICE_SELECT"),
+                    String::from("1002:73bf")
+                ),
+            ]
+        );
+
+        let nvidia_gpu =
+            select(GpuSelector::PciAddress(String::from("01:00.0"))).expect("Failed to select");
+
+        assert!(nvidia_gpu
+            .environment_variables()
+            .contains(&(String::from("__NV_PRIME_RENDER_OFFLOAD"), String::from("1"))));
+
+        assert_eq!(
+            select(GpuSelector::Index(0)).map(|gpu| gpu.boot_vga).ok(),
+            Some(true)
+        );
+        assert!(select(GpuSelector::Index(3)).is_err());
+        assert!(select(GpuSelector::PciAddress(String::from("0000:04:00.0"))).is_err());
+
+        // Addresses shorter than a domain don't panic.
+        let short_address = Gpu {
+            pci_address: String::from("0:1"),
+            vendor_id: 0x1002,
+            device_id: 0x73bf,
+            driver: None,
+            boot_vga: false,
+        };
+
+        assert!(GpuSelector::PciAddress(String::from("1"))
+            .select_from(vec![short_address.clone()])
+            .is_ok());
+        assert!(GpuSelector::PciAddress(String::from("01:00.0"))
+            .select_from(vec![short_address])
+            .is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use super::{
+    _default_fps_limit, _default_gamemode, _default_mangohud,
+    gamescope::{
+        _default_adaptive_sync, _default_expose_wayland, _default_force_grab_cursor, _default_hdr,
+        _default_mangoapp, _default_start_as_fullscreen, _default_steam, _default_tearing,
+    },
+    log_retention::{
+        _default_max_age_days, _default_max_sessions, _default_max_total_size_mib,
+        _default_uncompressed_sessions,
+    },
+    EnvironmentVariables, GameConfig, Gamescope, GamescopeBackend, GpuSelector,
+    HdrInverseToneMapping, LogCompression, LogRetention, ResolutionFallback, ScreenResolution,
+    TouchMode, UpscaleFilter, UpscaleScaler, VulkanDriver,
+};
+use serde::{Deserialize, Deserializer};
+
+// A config file where every field is optional, so that multiple files can be merged on top of
+// each other before the remaining fields are filled in with their defaults.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct GameConfigLayer {
+    // Names of the profiles in the profiles directory this layer is merged on top of.
+    pub inherits: Option<Vec<String>>,
+
+    pub gamemode: Option<bool>,
+    pub mangohud: Option<bool>,
+    pub vulkan_driver: Option<VulkanDriver>,
+    pub gpu: Option<GpuSelector>,
+    pub fps_limit: Option<u32>,
+
+    // `None` means the field wasn't specified, `Some(None)` means gamescope was disabled.
+    #[serde(default, deserialize_with = "deserialize_specified")]
+    pub gamescope: Option<Option<GamescopeLayer>>,
+
+    pub environment_variables: Option<EnvironmentVariables>,
+    pub log_retention: Option<LogRetentionLayer>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct GamescopeLayer {
+    pub source_resolution: Option<ScreenResolution>,
+    pub output_resolution: Option<ScreenResolution>,
+    pub resolution_fallback: Option<ResolutionFallback>,
+    pub start_as_fullscreen: Option<bool>,
+    pub force_grab_cursor: Option<bool>,
+    pub tearing: Option<bool>,
+    pub mangoapp: Option<bool>,
+    pub backend: Option<GamescopeBackend>,
+    pub expose_wayland: Option<bool>,
+    pub upscale_filter: Option<UpscaleFilter>,
+    pub upscale_scaler: Option<UpscaleScaler>,
+    pub sharpness: Option<u8>,
+    pub hdr: Option<bool>,
+    pub hdr_itm: Option<HdrInverseToneMapping>,
+    pub sdr_gamut_wideness: Option<f32>,
+    pub adaptive_sync: Option<bool>,
+    pub prefer_output: Option<Vec<String>>,
+    pub default_touch_mode: Option<TouchMode>,
+    pub steam: Option<bool>,
+    pub prefer_vk_device: Option<String>,
+    pub extra_arguments: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LogRetentionLayer {
+    // `Some(None)` means the limit was removed.
+    #[serde(default, deserialize_with = "deserialize_specified")]
+    pub max_sessions: Option<Option<usize>>,
+    #[serde(default, deserialize_with = "deserialize_specified")]
+    pub max_total_size_mib: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "deserialize_specified")]
+    pub max_age_days: Option<Option<u32>>,
+    pub compression: Option<LogCompression>,
+    pub uncompressed_sessions: Option<usize>,
+}
+
+fn deserialize_specified<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+impl GameConfigLayer {
+    // Fields set in `over` take precedence, environment variables are appended so that their
+    // operations apply on top of the ones in `self`.
+    // Inheritance has to be resolved before merging, the merged layer doesn't inherit anything.
+    pub fn merge(self, over: Self) -> Self {
+        let gamescope = match (self.gamescope, over.gamescope) {
+            (Some(Some(base)), Some(Some(over))) => Some(Some(base.merge(over))),
+            (base, None) => base,
+            (_, over) => over,
+        };
+
+        let log_retention = match (self.log_retention, over.log_retention) {
+            (Some(base), Some(over)) => Some(base.merge(over)),
+            (base, over) => over.or(base),
+        };
+
+        let environment_variables = match (self.environment_variables, over.environment_variables) {
+            (Some(mut base), Some(over)) => {
+                base.extend(over);
+
+                Some(base)
+            }
+            (base, over) => over.or(base),
+        };
+
+        Self {
+            inherits: None,
+            gamemode: over.gamemode.or(self.gamemode),
+            mangohud: over.mangohud.or(self.mangohud),
+            vulkan_driver: over.vulkan_driver.or(self.vulkan_driver),
+            gpu: over.gpu.or(self.gpu),
+            fps_limit: over.fps_limit.or(self.fps_limit),
+            gamescope,
+            environment_variables,
+            log_retention,
+        }
+    }
+
+    pub fn into_game_config(self) -> GameConfig {
+        GameConfig {
+            gamemode: self.gamemode.unwrap_or_else(_default_gamemode),
+            mangohud: self.mangohud.unwrap_or_else(_default_mangohud),
+            vulkan_driver: self.vulkan_driver.unwrap_or_default(),
+            gpu: self.gpu,
+            fps_limit: self.fps_limit.unwrap_or_else(_default_fps_limit),
+            gamescope: self.gamescope.flatten().map(GamescopeLayer::into_gamescope),
+            environment_variables: self.environment_variables.unwrap_or_default(),
+            log_retention: self
+                .log_retention
+                .map(LogRetentionLayer::into_log_retention)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl GamescopeLayer {
+    // Extra arguments are appended, like environment variables.
+    pub fn merge(self, over: Self) -> Self {
+        let extra_arguments = match (self.extra_arguments, over.extra_arguments) {
+            (Some(mut base), Some(over)) => {
+                base.extend(over);
+
+                Some(base)
+            }
+            (base, over) => over.or(base),
+        };
+
+        Self {
+            source_resolution: over.source_resolution.or(self.source_resolution),
+            output_resolution: over.output_resolution.or(self.output_resolution),
+            resolution_fallback: over.resolution_fallback.or(self.resolution_fallback),
+            start_as_fullscreen: over.start_as_fullscreen.or(self.start_as_fullscreen),
+            force_grab_cursor: over.force_grab_cursor.or(self.force_grab_cursor),
+            tearing: over.tearing.or(self.tearing),
+            mangoapp: over.mangoapp.or(self.mangoapp),
+            backend: over.backend.or(self.backend),
+            expose_wayland: over.expose_wayland.or(self.expose_wayland),
+            upscale_filter: over.upscale_filter.or(self.upscale_filter),
+            upscale_scaler: over.upscale_scaler.or(self.upscale_scaler),
+            sharpness: over.sharpness.or(self.sharpness),
+            hdr: over.hdr.or(self.hdr),
+            hdr_itm: over.hdr_itm.or(self.hdr_itm),
+            sdr_gamut_wideness: over.sdr_gamut_wideness.or(self.sdr_gamut_wideness),
+            adaptive_sync: over.adaptive_sync.or(self.adaptive_sync),
+            prefer_output: over.prefer_output.or(self.prefer_output),
+            default_touch_mode: over.default_touch_mode.or(self.default_touch_mode),
+            steam: over.steam.or(self.steam),
+            prefer_vk_device: over.prefer_vk_device.or(self.prefer_vk_device),
+            extra_arguments,
+        }
+    }
+
+    pub fn into_gamescope(self) -> Gamescope {
+        Gamescope {
+            source_resolution: self.source_resolution.unwrap_or_default(),
+            output_resolution: self.output_resolution,
+            resolution_fallback: self.resolution_fallback.unwrap_or_default(),
+            start_as_fullscreen: self
+                .start_as_fullscreen
+                .unwrap_or_else(_default_start_as_fullscreen),
+            force_grab_cursor: self
+                .force_grab_cursor
+                .unwrap_or_else(_default_force_grab_cursor),
+            tearing: self.tearing.unwrap_or_else(_default_tearing),
+            mangoapp: self.mangoapp.unwrap_or_else(_default_mangoapp),
+            backend: self.backend.unwrap_or_default(),
+            expose_wayland: self.expose_wayland.unwrap_or_else(_default_expose_wayland),
+            upscale_filter: self.upscale_filter.unwrap_or_default(),
+            upscale_scaler: self.upscale_scaler.unwrap_or_default(),
+            sharpness: self.sharpness,
+            hdr: self.hdr.unwrap_or_else(_default_hdr),
+            hdr_itm: self.hdr_itm,
+            sdr_gamut_wideness: self.sdr_gamut_wideness,
+            adaptive_sync: self.adaptive_sync.unwrap_or_else(_default_adaptive_sync),
+            prefer_output: self.prefer_output.unwrap_or_default(),
+            default_touch_mode: self.default_touch_mode,
+            steam: self.steam.unwrap_or_else(_default_steam),
+            prefer_vk_device: self.prefer_vk_device,
+            extra_arguments: self.extra_arguments.unwrap_or_default(),
+        }
+    }
+}
+
+impl LogRetentionLayer {
+    pub fn merge(self, over: Self) -> Self {
+        Self {
+            max_sessions: over.max_sessions.or(self.max_sessions),
+            max_total_size_mib: over.max_total_size_mib.or(self.max_total_size_mib),
+            max_age_days: over.max_age_days.or(self.max_age_days),
+            compression: over.compression.or(self.compression),
+            uncompressed_sessions: over.uncompressed_sessions.or(self.uncompressed_sessions),
+        }
+    }
+
+    pub fn into_log_retention(self) -> LogRetention {
+        LogRetention {
+            max_sessions: self.max_sessions.unwrap_or_else(_default_max_sessions),
+            max_total_size_mib: self
+                .max_total_size_mib
+                .unwrap_or_else(_default_max_total_size_mib),
+            max_age_days: self.max_age_days.unwrap_or_else(_default_max_age_days),
+            compression: self.compression.unwrap_or_default(),
+            uncompressed_sessions: self
+                .uncompressed_sessions
+                .unwrap_or_else(_default_uncompressed_sessions),
+        }
+    }
+}
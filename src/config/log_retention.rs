@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+pub(super) const fn _default_max_sessions() -> Option<usize> {
+    Some(20)
+}
+
+pub(super) const fn _default_max_total_size_mib() -> Option<u64> {
+    Some(2048)
+}
+
+pub(super) const fn _default_max_age_days() -> Option<u32> {
+    None
+}
+
+pub(super) const fn _default_uncompressed_sessions() -> usize {
+    1
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum LogCompression {
+    None,
+    Gzip,
+    #[default]
+    Zstd,
+}
+
+impl LogCompression {
+    pub fn as_file_extension(&self) -> Option<&str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+        }
+    }
+}
+
+// What happens to the persistent output logs of a game after each launch and with
+// `logs prune`. Sessions are removed oldest first until every limit is met, the newest
+// session is always kept.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LogRetention {
+    #[serde(default = "_default_max_sessions")]
+    pub max_sessions: Option<usize>,
+
+    // Of every session of the game together, after compression.
+    #[serde(default = "_default_max_total_size_mib")]
+    pub max_total_size_mib: Option<u64>,
+
+    #[serde(default = "_default_max_age_days")]
+    pub max_age_days: Option<u32>,
+
+    #[serde(default)]
+    pub compression: LogCompression,
+
+    // The newest sessions are kept uncompressed, so that they can be read directly.
+    #[serde(default = "_default_uncompressed_sessions")]
+    pub uncompressed_sessions: usize,
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            max_sessions: _default_max_sessions(),
+            max_total_size_mib: _default_max_total_size_mib(),
+            max_age_days: _default_max_age_days(),
+            compression: LogCompression::default(),
+            uncompressed_sessions: _default_uncompressed_sessions(),
+        }
+    }
+}
+
+impl LogRetention {
+    pub fn max_total_size_bytes(&self) -> Option<u64> {
+        self.max_total_size_mib
+            .map(|mib| mib.saturating_mul(1024 * 1024))
+    }
+}
@@ -1,23 +1,44 @@
 #![allow(unused)]
 
 mod config_file;
+mod environment_variables;
 mod gamescope;
+mod gamescope_help;
+mod gpu;
+mod layer;
+mod log_retention;
 mod parsing;
 mod screen_resolution;
+mod template;
+mod validation;
 mod vulkan_driver;
 
-pub use config_file::GameConfigFile;
-pub use gamescope::Gamescope;
-pub use screen_resolution::ScreenResolution;
-use serde::Deserialize;
+pub use config_file::{config_directory, game_config_directory, profile_directory, GameConfigFile};
+pub use environment_variables::{EnvironmentOperation, EnvironmentVariables};
+pub use gamescope::{
+    Gamescope, GamescopeBackend, HdrInverseToneMapping, TouchMode, UpscaleFilter, UpscaleScaler,
+};
+pub use gamescope_help::GamescopeHelp;
+pub use gpu::{enumerate_gpus, installed_gpus, Gpu, GpuError, GpuSelector, GpuVendor};
+pub use layer::{GameConfigLayer, GamescopeLayer, LogRetentionLayer};
+pub use log_retention::{LogCompression, LogRetention};
+pub use parsing::LayerSource;
+pub use screen_resolution::{ResolutionFallback, ScreenResolution};
+use serde::Serialize;
 use std::path::PathBuf;
+pub use template::{game_config_template, serialize_game_config};
 use tokio::io;
-pub use vulkan_driver::VulkanDriver;
+pub use validation::{
+    check_game_config, validate_game_config_file, Diagnostic, DiagnosticSeverity,
+};
+pub use vulkan_driver::{
+    discover_icd_manifests, icd_directories, IcdManifest, VulkanDriver, VulkanDriverError,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum GameConfigError {
     #[error(
-        "The root user can't have a game configuration directory. Run the command as a normal user or specify a configuration directory with {}", format!("${}_GAME_CONFIG_DIR", env!("CARGO_PKG_NAME").to_uppercase())
+        "The root user can't have a configuration directory. Run the command as a normal user or specify a configuration directory with {} or {}", format!("${}_CONFIG_DIR", env!("CARGO_PKG_NAME").to_uppercase()), format!("${}_GAME_CONFIG_DIR", env!("CARGO_PKG_NAME").to_uppercase())
     )]
     UserIsRoot,
 
@@ -33,6 +54,18 @@ pub enum GameConfigError {
     #[error("Failed to parse the configuration file at `{0}`, position {2}:{3}. {1}.")]
     ParseError(PathBuf, String, u16, u16),
 
+    #[error("The profile `{0}` doesn't exist, expected it at `{1}`")]
+    MissingProfile(String, PathBuf),
+
+    #[error("The profiles inherit from each other in a cycle: {}", .0.join(" -> "))]
+    CyclicProfileInheritance(Vec<String>),
+
+    #[error("Failed to look up the current user, see: {0}")]
+    LookupCurrentUser(nix::Error),
+
+    #[error("The current user (uid {0}) doesn't exist in the user database")]
+    MissingCurrentUser(nix::unistd::Uid),
+
     #[error("Unexpected IO error, see: {0:#?}")]
     UnexpectedIoError(io::Error),
 }
@@ -40,6 +73,7 @@ pub enum GameConfigError {
 const fn _default_gamemode() -> bool {
     true
 }
+
 const fn _default_mangohud() -> bool {
     true
 }
@@ -48,29 +82,20 @@ const fn _default_fps_limit() -> u32 {
     0
 }
 
-const fn _default_environment_variables() -> Vec<(String, String)> {
-    vec![]
+const fn _default_environment_variables() -> EnvironmentVariables {
+    EnvironmentVariables::new()
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Debug, Clone)]
 pub struct GameConfig {
-    #[serde(default = "_default_gamemode")]
     pub gamemode: bool,
-
-    #[serde(default = "_default_mangohud")]
     pub mangohud: bool,
-
-    #[serde(default)]
     pub vulkan_driver: VulkanDriver,
-
-    #[serde(default = "_default_fps_limit")]
+    pub gpu: Option<GpuSelector>,
     pub fps_limit: u32,
-
-    #[serde(default)]
     pub gamescope: Option<Gamescope>,
-
-    #[serde(default = "_default_environment_variables")]
-    pub environment_variables: Vec<(String, String)>,
+    pub environment_variables: EnvironmentVariables,
+    pub log_retention: LogRetention,
 }
 
 impl Default for GameConfig {
@@ -79,9 +104,11 @@ impl Default for GameConfig {
             gamemode: _default_gamemode(),
             mangohud: _default_mangohud(),
             vulkan_driver: VulkanDriver::default(),
+            gpu: None,
             fps_limit: _default_fps_limit(),
             gamescope: Some(Gamescope::default()),
             environment_variables: _default_environment_variables(),
+            log_retention: LogRetention::default(),
         }
     }
 }
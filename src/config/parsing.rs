@@ -1,14 +1,54 @@
-use super::config_file::GameConfigFile;
-use super::{GameConfig, GameConfigError};
+use super::config_file::{profile_directory, GameConfigFile};
+use super::{GameConfig, GameConfigError, GameConfigLayer};
+use ron::extensions::Extensions;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
-impl GameConfig {
-    pub async fn from_game_config_file(mut file: GameConfigFile) -> Result<Self, GameConfigError> {
+// A file a config was merged from, so that diagnostics can point into the file that set a field.
+#[derive(Debug, Clone)]
+pub struct LayerSource {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+// State that's shared while resolving the profiles a single file inherits.
+#[derive(Default)]
+struct InheritanceResolution {
+    chain: Vec<String>,
+    merged_profiles: HashSet<String>,
+    // In the order they're merged, profiles before the files that inherit them.
+    sources: Vec<LayerSource>,
+}
+
+impl GameConfigLayer {
+    pub fn from_ron_str(contents: &str) -> ron::error::SpannedResult<Self> {
+        // Every field of a layer is optional, implicit_some lets them be written without `Some(..)`.
+        let ron_options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+
+        ron_options.from_str::<GameConfigLayer>(contents)
+    }
+
+    pub async fn from_game_config_file(file: GameConfigFile) -> Result<Self, GameConfigError> {
+        Ok(Self::from_game_config_file_with_source(file).await?.0)
+    }
+
+    async fn from_game_config_file_with_source(
+        mut file: GameConfigFile,
+    ) -> Result<(Self, LayerSource), GameConfigError> {
         let contents = file.read_to_string().await?;
 
-        match ron::from_str::<GameConfig>(&contents) {
-            Ok(launch_config) => Ok(launch_config),
+        match Self::from_ron_str(&contents) {
+            Ok(layer) => Ok((
+                layer,
+                LayerSource {
+                    path: file.path,
+                    contents,
+                },
+            )),
             Err(error) => {
-                let explanation = error.code.to_string();
+                let explanation = describe_parse_error(&error.code);
                 let position = error.position;
 
                 let (line, column) = (position.line as u16, position.col as u16);
@@ -22,11 +62,142 @@ impl GameConfig {
             }
         }
     }
+
+    // Merges the profiles this layer inherits from underneath it, in the order they're listed.
+    // A profile that's inherited more than once, e.g. a base shared by two profiles, is only
+    // merged the first time, so that its environment variables aren't applied twice.
+    pub async fn resolve_inheritance(
+        self,
+        profile_dir_path: &Path,
+    ) -> Result<Self, GameConfigError> {
+        self.resolve_inheritance_with(profile_dir_path, &mut InheritanceResolution::default())
+            .await
+    }
+
+    async fn resolve_inheritance_with(
+        mut self,
+        profile_dir_path: &Path,
+        resolution: &mut InheritanceResolution,
+    ) -> Result<Self, GameConfigError> {
+        let mut resolved = GameConfigLayer::default();
+
+        for profile_name in self.inherits.take().unwrap_or_default() {
+            if let Some(position) = resolution
+                .chain
+                .iter()
+                .position(|name| *name == profile_name)
+            {
+                let mut cycle = resolution.chain[position..].to_vec();
+
+                cycle.push(profile_name);
+
+                return Err(GameConfigError::CyclicProfileInheritance(cycle));
+            }
+
+            if resolution.merged_profiles.contains(&profile_name) {
+                continue;
+            }
+
+            let profile_file = GameConfigFile::from_profile_name(profile_dir_path, &profile_name)?;
+            let (profile, source) =
+                GameConfigLayer::from_game_config_file_with_source(profile_file).await?;
+
+            resolution.chain.push(profile_name.clone());
+
+            let profile =
+                Box::pin(profile.resolve_inheritance_with(profile_dir_path, resolution)).await?;
+
+            resolution.chain.pop();
+            resolution.merged_profiles.insert(profile_name);
+            resolution.sources.push(source);
+
+            resolved = resolved.merge(profile);
+        }
+
+        Ok(resolved.merge(self))
+    }
+}
+
+fn describe_parse_error(error: &ron::Error) -> String {
+    if let ron::Error::NoSuchStructField {
+        expected, found, ..
+    } = error
+    {
+        let closest_field = expected
+            .iter()
+            .map(|field| (strsim::levenshtein(found, field), field))
+            .filter(|(distance, field)| *distance <= field.len() / 2)
+            .min_by_key(|(distance, _)| *distance);
+
+        if let Some((_, closest_field)) = closest_field {
+            return format!("{error}. The closest valid field is `{closest_field}`");
+        }
+    }
+
+    error.to_string()
+}
+
+impl GameConfig {
+    pub async fn from_game_config_file(file: GameConfigFile) -> Result<Self, GameConfigError> {
+        Self::from_game_config_files(vec![file]).await
+    }
+
+    // Merges the files on top of each other, later files override the earlier ones.
+    pub async fn from_game_config_files(
+        files: Vec<GameConfigFile>,
+    ) -> Result<Self, GameConfigError> {
+        Ok(Self::from_game_config_files_with_sources(files).await?.0)
+    }
+
+    // Also returns every file that was merged, including the inherited profiles, in the order
+    // they were merged.
+    pub async fn from_game_config_files_with_sources(
+        files: Vec<GameConfigFile>,
+    ) -> Result<(Self, Vec<LayerSource>), GameConfigError> {
+        let mut layer = GameConfigLayer::default();
+        let mut sources = Vec::new();
+
+        for file in files {
+            let (mut file_layer, source) =
+                GameConfigLayer::from_game_config_file_with_source(file).await?;
+
+            if file_layer.inherits.is_some() {
+                let mut resolution = InheritanceResolution::default();
+
+                file_layer = file_layer
+                    .resolve_inheritance_with(&profile_directory()?, &mut resolution)
+                    .await?;
+
+                sources.append(&mut resolution.sources);
+            }
+
+            sources.push(source);
+            layer = layer.merge(file_layer);
+        }
+
+        Ok((layer.into_game_config(), sources))
+    }
+
+    // The game's config merged on top of `defaults.ron`, or the built-in defaults if neither exists.
+    pub async fn load(game_config_file: Option<GameConfigFile>) -> Result<Self, GameConfigError> {
+        let defaults_file = GameConfigFile::defaults().await?;
+
+        if defaults_file.is_none() && game_config_file.is_none() {
+            return Ok(Self::default());
+        }
+
+        Self::from_game_config_files(defaults_file.into_iter().chain(game_config_file).collect())
+            .await
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::{config_file::GameConfigFile, GameConfig, ScreenResolution, VulkanDriver};
+    use super::super::{
+        config_file::GameConfigFile, EnvironmentOperation, EnvironmentVariables, GameConfig,
+        GameConfigError, GameConfigLayer, LogCompression, LogRetention, ScreenResolution,
+        VulkanDriver,
+    };
     use crate::UPPERCASE_PACKAGE_NAME;
     use color_eyre::eyre;
     use lazy_static::lazy_static;
@@ -95,4 +266,267 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn merge_game_config_on_top_of_defaults() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("merge_game_config_on_top_of_defaults")?;
+
+        let defaults_path = temp_dir.path().join("defaults.ron");
+        let game_config_path = temp_dir.path().join("582660.ron");
+
+        fs::write(
+            &defaults_path,
+            r#"(
+                gamemode: false,
+                fps_limit: 60,
+                gamescope: Some((
+                    source_resolution: Custom(2560, 1440),
+                    tearing: false,
+                )),
+                environment_variables: [("DXVK_ASYNC", "1")],
+                log_retention: (
+                    max_sessions: Some(5),
+                    compression: Gzip,
+                ),
+            )"#,
+        )
+        .await?;
+
+        fs::write(
+            &game_config_path,
+            r#"(
+                fps_limit: 90,
+                gamescope: Some((
+                    tearing: true,
+                )),
+                environment_variables: [("PROTON_ENABLE_NGX_UPDATER", "1")],
+                log_retention: (
+                    max_sessions: None,
+                    max_age_days: Some(7),
+                ),
+            )"#,
+        )
+        .await?;
+
+        let config = GameConfig::from_game_config_files(vec![
+            GameConfigFile {
+                path: defaults_path,
+            },
+            GameConfigFile {
+                path: game_config_path,
+            },
+        ])
+        .await?;
+
+        assert!(!config.gamemode);
+        assert!(config.mangohud);
+        assert_eq!(config.fps_limit, 90);
+
+        assert_eq!(
+            config.environment_variables,
+            EnvironmentVariables::from(vec![
+                (
+                    String::from("DXVK_ASYNC"),
+                    EnvironmentOperation::Set(String::from("1"))
+                ),
+                (
+                    String::from("PROTON_ENABLE_NGX_UPDATER"),
+                    EnvironmentOperation::Set(String::from("1"))
+                ),
+            ])
+        );
+
+        let gamescope_config = config.gamescope.expect("Failed to get gamescope config");
+
+        assert_eq!(
+            gamescope_config.source_resolution,
+            ScreenResolution::Custom(2560, 1440)
+        );
+
+        assert!(gamescope_config.tearing);
+
+        assert_eq!(
+            config.log_retention,
+            LogRetention {
+                max_sessions: None,
+                max_age_days: Some(7),
+                compression: LogCompression::Gzip,
+                ..LogRetention::default()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn game_config_can_disable_gamescope_from_defaults() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("game_config_can_disable_gamescope_from_defaults")?;
+
+        let defaults_path = temp_dir.path().join("defaults.ron");
+        let game_config_path = temp_dir.path().join("582660.ron");
+
+        fs::write(&defaults_path, "(gamescope: Some(()))").await?;
+        fs::write(&game_config_path, "(gamescope: None)").await?;
+
+        let config = GameConfig::from_game_config_files(vec![
+            GameConfigFile {
+                path: defaults_path,
+            },
+            GameConfigFile {
+                path: game_config_path,
+            },
+        ])
+        .await?;
+
+        assert!(config.gamescope.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resolve_multi_level_profile_inheritance() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("resolve_multi_level_profile_inheritance")?;
+        let profile_dir = temp_dir.path();
+
+        fs::write(
+            profile_dir.join("base.ron"),
+            r#"(
+                fps_limit: 60,
+                gamescope: Some((tearing: false, source_resolution: Custom(1280, 800))),
+            )"#,
+        )
+        .await?;
+
+        fs::write(
+            profile_dir.join("competitive.ron"),
+            r#"(
+                inherits: ["base"],
+                gamescope: Some((tearing: true)),
+                environment_variables: [("DXVK_ASYNC", "1")],
+            )"#,
+        )
+        .await?;
+
+        fs::write(profile_dir.join("handheld.ron"), "(fps_limit: 40)").await?;
+
+        let game_config_path = profile_dir.join("game.ron");
+
+        fs::write(
+            &game_config_path,
+            r#"(inherits: ["competitive", "handheld"], mangohud: false)"#,
+        )
+        .await?;
+
+        let layer = GameConfigLayer::from_game_config_file(GameConfigFile {
+            path: game_config_path,
+        })
+        .await?
+        .resolve_inheritance(profile_dir)
+        .await?;
+
+        let config = layer.into_game_config();
+
+        assert_eq!(config.fps_limit, 40);
+        assert!(!config.mangohud);
+        assert_eq!(config.environment_variables.len(), 1);
+
+        let gamescope_config = config.gamescope.expect("Failed to get gamescope config");
+
+        assert!(gamescope_config.tearing);
+        assert_eq!(
+            gamescope_config.source_resolution,
+            ScreenResolution::Custom(1280, 800)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn merge_a_shared_base_profile_once() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("merge_a_shared_base_profile_once")?;
+        let profile_dir = temp_dir.path();
+
+        fs::write(
+            profile_dir.join("base.ron"),
+            r#"(
+                gamescope: Some((extra_arguments: ["--rt"])),
+                environment_variables: {"PATH": (prepend: "/opt/x")},
+            )"#,
+        )
+        .await?;
+
+        fs::write(profile_dir.join("left.ron"), r#"(inherits: ["base"])"#).await?;
+        fs::write(profile_dir.join("right.ron"), r#"(inherits: ["base"])"#).await?;
+
+        let game_config_path = profile_dir.join("game.ron");
+
+        fs::write(&game_config_path, r#"(inherits: ["left", "right"])"#).await?;
+
+        let config = GameConfigLayer::from_game_config_file(GameConfigFile {
+            path: game_config_path,
+        })
+        .await?
+        .resolve_inheritance(profile_dir)
+        .await?
+        .into_game_config();
+
+        assert_eq!(config.environment_variables.len(), 1);
+        assert_eq!(
+            config
+                .gamescope
+                .expect("Failed to get gamescope config")
+                .extra_arguments,
+            ["--rt"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn detect_missing_and_cyclic_profiles() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("detect_missing_and_cyclic_profiles")?;
+        let profile_dir = temp_dir.path();
+
+        fs::write(profile_dir.join("a.ron"), r#"(inherits: ["b"])"#).await?;
+        fs::write(profile_dir.join("b.ron"), r#"(inherits: ["a"])"#).await?;
+
+        let cyclic_layer = GameConfigLayer {
+            inherits: Some(vec![String::from("a")]),
+            ..Default::default()
+        };
+
+        match cyclic_layer.resolve_inheritance(profile_dir).await {
+            Err(GameConfigError::CyclicProfileInheritance(cycle)) => {
+                assert_eq!(cycle, ["a", "b", "a"])
+            }
+            _ => panic!("Expected the cyclic inheritance to be detected"),
+        }
+
+        let missing_layer = GameConfigLayer {
+            inherits: Some(vec![String::from("missing")]),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            missing_layer.resolve_inheritance(profile_dir).await,
+            Err(GameConfigError::MissingProfile(name, _)) if name == "missing"
+        ));
+
+        fs::create_dir(profile_dir.join("nested")).await?;
+        fs::write(profile_dir.join("nested/b.ron"), "()").await?;
+
+        for name in ["../outside", "nested/b", ".hidden", "/etc/passwd"] {
+            let layer = GameConfigLayer {
+                inherits: Some(vec![String::from(name)]),
+                ..Default::default()
+            };
+
+            assert!(matches!(
+                layer.resolve_inheritance(profile_dir).await,
+                Err(GameConfigError::MissingProfile(missing, _)) if missing == name
+            ));
+        }
+
+        Ok(())
+    }
 }
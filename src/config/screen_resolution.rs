@@ -3,43 +3,199 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tokio::{io, process::Command};
 
-use hyprland::{
-    data::{Monitor as HyprlandMonitor, Monitors as HyprlandMonitors},
-    shared::{HyprData, HyprDataActive},
+use crate::resolution_provider::{
+    detect_resolution_provider, Output, ResolutionProvider, ResolutionProviderError,
 };
 
-#[derive(Deserialize, Debug, PartialEq, Default)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
 pub enum ScreenResolution {
     #[default]
     Native,
     Custom(u16, u16),
+    // An output by its connector name, e.g. `Monitor("DP-1")`.
+    Monitor(String),
+    // A fraction of the output resolution, e.g. `Scale(0.5)` renders 4K output at 1080p.
+    Scale(f32),
 }
 
-impl ScreenResolution {
-    pub fn as_gamescope_command_argument(&self) -> String {
-        match self {
-            Self::Custom(width, height) => format!("-w {width} -h {height}"),
-            Self::Native => match get_native_screen_resolution() {
-                Err(error) => panic!("Failed to get the native screen resolution, see: {error:#?}"),
-                Ok(screen_resolution) => {
-                    let (width, height) = screen_resolution;
-
-                    format!("-w {width} -h {height}")
-                }
-            },
+// What to do when the native resolution can't be detected.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+pub enum ResolutionFallback {
+    #[default]
+    Abort,
+    Custom(u16, u16),
+    // Leave out `-w`/`-h`, gamescope then picks a resolution on its own.
+    Gamescope,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    pub width: u16,
+    pub height: u16,
+    pub refresh_rate: Option<f32>,
+}
+
+impl From<Output> for Monitor {
+    fn from(output: Output) -> Self {
+        Self {
+            width: output.width,
+            height: output.height,
+            refresh_rate: output.refresh_rate,
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
-struct Monitor {
-    width: u32,
-    height: u32,
-    focused: bool,
+impl ScreenResolution {
+    pub fn is_valid_scale(factor: f32) -> bool {
+        factor > 0.0 && factor <= 1.0
+    }
+
+    // `Scale` is relative to `output`, or to the focused monitor if there's no output resolution.
+    // `None` if the resolution is left up to gamescope.
+    pub fn resolve(
+        &self,
+        output: Option<&Monitor>,
+        fallback: &ResolutionFallback,
+    ) -> Result<Option<Monitor>, ResolutionProviderError> {
+        self.resolve_with(detect_resolution_provider, output, fallback)
+    }
+
+    fn resolve_with(
+        &self,
+        detect_resolution_provider: impl FnOnce() -> Box<dyn ResolutionProvider>,
+        output: Option<&Monitor>,
+        fallback: &ResolutionFallback,
+    ) -> Result<Option<Monitor>, ResolutionProviderError> {
+        if let Self::Scale(factor) = self {
+            if !Self::is_valid_scale(*factor) {
+                return Err(ResolutionProviderError::InvalidScale(*factor));
+            }
+        }
+
+        let detected_monitor = match self {
+            Self::Custom(width, height) => {
+                return Ok(Some(Monitor {
+                    width: *width,
+                    height: *height,
+                    refresh_rate: None,
+                }))
+            }
+            Self::Native => detect_resolution_provider()
+                .focused_output()
+                .map(Monitor::from),
+            Self::Monitor(name) => detect_resolution_provider().output(name).map(Monitor::from),
+            Self::Scale(factor) => match output {
+                Some(output) => Ok(output.clone()),
+                None => detect_resolution_provider()
+                    .focused_output()
+                    .map(Monitor::from),
+            }
+            .map(|monitor| Monitor {
+                width: (f32::from(monitor.width) * factor).round() as u16,
+                height: (f32::from(monitor.height) * factor).round() as u16,
+                refresh_rate: monitor.refresh_rate,
+            }),
+        };
+
+        match (detected_monitor, fallback) {
+            (Ok(monitor), _) => Ok(Some(monitor)),
+            (Err(error), ResolutionFallback::Abort) => Err(error),
+            (Err(error), ResolutionFallback::Custom(width, height)) => {
+                tracing::warn!("Failed to detect the screen resolution, falling back to {width}x{height}. {error}");
+
+                Ok(Some(Monitor {
+                    width: *width,
+                    height: *height,
+                    refresh_rate: None,
+                }))
+            }
+            (Err(error), ResolutionFallback::Gamescope) => {
+                tracing::warn!(
+                    "Failed to detect the screen resolution, letting gamescope decide. {error}"
+                );
+
+                Ok(None)
+            }
+        }
+    }
 }
 
-fn get_native_screen_resolution() -> hyprland::Result<(u16, u16)> {
-    let active_monitor_info = HyprlandMonitor::get_active()?;
+#[cfg(test)]
+mod tests {
+    use super::{Monitor, ResolutionFallback, ScreenResolution};
+    use crate::resolution_provider::{Output, ResolutionProvider, ResolutionProviderError};
+
+    struct FailingProvider;
+
+    impl ResolutionProvider for FailingProvider {
+        fn name(&self) -> &'static str {
+            "FailingProvider"
+        }
+
+        fn outputs(&self) -> Result<Vec<Output>, ResolutionProviderError> {
+            Err(ResolutionProviderError::NoOutputs(self.name()))
+        }
+    }
+
+    fn resolve(
+        resolution: ScreenResolution,
+        fallback: ResolutionFallback,
+    ) -> Result<Option<Monitor>, ResolutionProviderError> {
+        resolution.resolve_with(|| Box::new(FailingProvider), None, &fallback)
+    }
+
+    #[test]
+    fn fall_back_when_the_resolution_cant_be_detected() {
+        assert!(matches!(
+            resolve(ScreenResolution::Native, ResolutionFallback::Abort),
+            Err(ResolutionProviderError::NoOutputs("FailingProvider"))
+        ));
+        assert!(matches!(
+            resolve(
+                ScreenResolution::Monitor(String::from("DP-1")),
+                ResolutionFallback::Abort
+            ),
+            Err(ResolutionProviderError::NoOutputs("FailingProvider"))
+        ));
+
+        let custom = Monitor {
+            width: 1280,
+            height: 720,
+            refresh_rate: None,
+        };
 
-    Ok((active_monitor_info.width, active_monitor_info.height))
+        assert_eq!(
+            resolve(
+                ScreenResolution::Native,
+                ResolutionFallback::Custom(1280, 720)
+            )
+            .ok(),
+            Some(Some(custom.clone()))
+        );
+        assert_eq!(
+            resolve(ScreenResolution::Scale(0.5), ResolutionFallback::Gamescope).ok(),
+            Some(None)
+        );
+
+        // An invalid factor isn't a detection failure, so it doesn't fall back.
+        for factor in [0.0, -0.5, 1.5, f32::NAN] {
+            assert!(matches!(
+                resolve(
+                    ScreenResolution::Scale(factor),
+                    ResolutionFallback::Custom(1280, 720)
+                ),
+                Err(ResolutionProviderError::InvalidScale(_))
+            ));
+        }
+
+        // Custom resolutions don't need to be detected.
+        assert_eq!(
+            resolve(
+                ScreenResolution::Custom(1280, 720),
+                ResolutionFallback::Abort
+            )
+            .ok(),
+            Some(Some(custom))
+        );
+    }
 }
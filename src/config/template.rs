@@ -0,0 +1,102 @@
+use super::GameConfig;
+use ron::ser::PrettyConfig;
+
+const TEMPLATE_HEADER: &str = "\
+// Every field is commented out and set to its default value, uncomment the ones you want to change.
+// Fields that aren't set here are taken from defaults.ron and the inherited profiles, if there are any.
+";
+
+pub fn serialize_game_config(config: &GameConfig) -> Result<String, ron::Error> {
+    ron::ser::to_string_pretty(config, PrettyConfig::new().struct_names(false))
+}
+
+pub fn game_config_template() -> Result<String, ron::Error> {
+    let serialized_defaults = serialize_game_config(&GameConfig::default())?;
+
+    let mut template = String::from(TEMPLATE_HEADER);
+    let lines: Vec<&str> = serialized_defaults.lines().collect();
+
+    if let Some((first_line, rest)) = lines.split_first() {
+        template.push_str(first_line);
+        template.push('\n');
+        template.push_str("    // inherits: [],\n");
+
+        for line in rest {
+            if *line == ")" {
+                template.push_str(line);
+            } else {
+                let line = line.strip_prefix("    ").unwrap_or(line);
+
+                template.push_str(&format!("    // {line}"));
+            }
+
+            template.push('\n');
+        }
+    }
+
+    Ok(template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::game_config_template;
+    use crate::config::{GameConfigLayer, GamescopeBackend};
+
+    #[test]
+    fn template_parses_and_mentions_every_field() {
+        let template = game_config_template().expect("Failed to generate the template");
+
+        for field in [
+            "inherits",
+            "gamemode",
+            "mangohud",
+            "vulkan_driver",
+            "gpu",
+            "fps_limit",
+            "gamescope",
+            "source_resolution",
+            "output_resolution",
+            "resolution_fallback",
+            "backend",
+            "upscale_filter",
+            "sharpness",
+            "hdr",
+            "adaptive_sync",
+            "prefer_output",
+            "steam",
+            "prefer_vk_device",
+            "extra_arguments",
+            "environment_variables",
+            "log_retention",
+            "max_sessions",
+        ] {
+            assert!(
+                template.contains(&format!("// {field}:"))
+                    || template.contains(&format!("//     {field}:")),
+                "The template doesn't mention `{field}`:\n{template}"
+            );
+        }
+
+        let layer = GameConfigLayer::from_ron_str(&template).expect("Failed to parse the template");
+
+        assert!(layer.gamemode.is_none());
+        assert!(layer.gamescope.is_none());
+
+        // Uncommenting everything should result in a valid config as well.
+        let uncommented = template
+            .lines()
+            .filter(|line| !line.starts_with("//"))
+            .map(|line| line.replacen("// ", "", 1))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let layer = GameConfigLayer::from_ron_str(&uncommented)
+            .expect("Failed to parse the uncommented template");
+
+        assert_eq!(layer.gamemode, Some(true));
+        assert!(matches!(
+            layer.gamescope,
+            Some(Some(gamescope)) if gamescope.backend == Some(GamescopeBackend::Auto)
+        ));
+    }
+}
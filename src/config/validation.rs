@@ -0,0 +1,581 @@
+use super::{
+    GameConfig, GameConfigError, GameConfigFile, GamescopeBackend, LayerSource, ScreenResolution,
+    UpscaleFilter,
+};
+use std::{fmt, iter::Peekable, path::PathBuf, str::Chars};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub path: Option<PathBuf>,
+    // 1-based line and column, the same as GameConfigError::ParseError.
+    pub position: Option<(u16, u16)>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == DiagnosticSeverity::Error
+    }
+}
+
+impl From<GameConfigError> for Diagnostic {
+    fn from(error: GameConfigError) -> Self {
+        match error {
+            GameConfigError::ParseError(path, explanation, line, column) => Self {
+                severity: DiagnosticSeverity::Error,
+                message: explanation,
+                path: Some(path),
+                position: Some((line, column)),
+            },
+            error => Self {
+                severity: DiagnosticSeverity::Error,
+                message: error.to_string(),
+                path: None,
+                position: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            DiagnosticSeverity::Warning => write!(formatter, "warning: ")?,
+            DiagnosticSeverity::Error => write!(formatter, "error: ")?,
+        }
+
+        if let Some(path) = &self.path {
+            write!(formatter, "{}", path.display())?;
+
+            if let Some((line, column)) = self.position {
+                write!(formatter, ":{line}:{column}")?;
+            }
+
+            write!(formatter, ": ")?;
+        }
+
+        write!(formatter, "{}", self.message)
+    }
+}
+
+// What a semantic problem is about, used to find its position in the config file.
+enum Subject {
+    Field(&'static str),
+    EnvironmentVariable(String),
+}
+
+struct Finding {
+    severity: DiagnosticSeverity,
+    subject: Subject,
+    message: String,
+}
+
+fn find_semantic_problems(config: &GameConfig) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if let Some(gamescope) = &config.gamescope {
+        if config.mangohud && gamescope.mangoapp {
+            findings.push(Finding {
+                severity: DiagnosticSeverity::Warning,
+                subject: Subject::Field("mangoapp"),
+                message: String::from("`mangohud` and gamescope's `mangoapp` are both enabled, the overlay will be drawn twice. Disable one of them"),
+            });
+        }
+
+        let resolutions = [
+            ("source_resolution", Some(&gamescope.source_resolution)),
+            ("output_resolution", gamescope.output_resolution.as_ref()),
+        ];
+
+        for (field, resolution) in resolutions {
+            if let Some(ScreenResolution::Scale(factor)) = resolution {
+                if !ScreenResolution::is_valid_scale(*factor) {
+                    findings.push(Finding {
+                        severity: DiagnosticSeverity::Error,
+                        subject: Subject::Field(field),
+                        message: format!("`{field}` can't be scaled by {factor}, the factor has to be greater than 0 and at most 1"),
+                    });
+                }
+            }
+        }
+
+        if let Some(sharpness) = gamescope.sharpness {
+            if sharpness > 20 {
+                findings.push(Finding {
+                    severity: DiagnosticSeverity::Error,
+                    subject: Subject::Field("sharpness"),
+                    message: format!(
+                        "`sharpness` has to be between 0 and 20, but it's {sharpness}"
+                    ),
+                });
+            } else if ![UpscaleFilter::Fsr, UpscaleFilter::Nis].contains(&gamescope.upscale_filter)
+            {
+                findings.push(Finding {
+                    severity: DiagnosticSeverity::Warning,
+                    subject: Subject::Field("sharpness"),
+                    message: format!("`sharpness` only has an effect with the `Fsr` and `Nis` upscale filters, but the filter is `{:?}`", gamescope.upscale_filter),
+                });
+            }
+        }
+
+        if let Some(sdr_gamut_wideness) = gamescope.sdr_gamut_wideness {
+            if !(0.0..=1.0).contains(&sdr_gamut_wideness) {
+                findings.push(Finding {
+                    severity: DiagnosticSeverity::Error,
+                    subject: Subject::Field("sdr_gamut_wideness"),
+                    message: format!("`sdr_gamut_wideness` has to be between 0 and 1, but it's {sdr_gamut_wideness}"),
+                });
+            }
+        }
+
+        if !gamescope.hdr && gamescope.hdr_itm.is_some() {
+            findings.push(Finding {
+                severity: DiagnosticSeverity::Warning,
+                subject: Subject::Field("hdr_itm"),
+                message: String::from("`hdr_itm` maps SDR content to HDR, but `hdr` isn't enabled"),
+            });
+        }
+
+        // In a desktop session the Auto backend picks a nested one, but from a TTY it picks DRM.
+        let may_use_drm =
+            [GamescopeBackend::Auto, GamescopeBackend::Drm].contains(&gamescope.backend);
+
+        if !may_use_drm && !gamescope.prefer_output.is_empty() {
+            findings.push(Finding {
+                severity: DiagnosticSeverity::Warning,
+                subject: Subject::Field("prefer_output"),
+                message: format!("`prefer_output` only has an effect with the `Drm` backend, but the backend is `{:?}`", gamescope.backend),
+            });
+        }
+
+        if !may_use_drm && gamescope.default_touch_mode.is_some() {
+            findings.push(Finding {
+                severity: DiagnosticSeverity::Warning,
+                subject: Subject::Field("default_touch_mode"),
+                message: format!("`default_touch_mode` only has an effect with the `Drm` backend, but the backend is `{:?}`", gamescope.backend),
+            });
+        }
+
+        if let Some(prefer_vk_device) = &gamescope.prefer_vk_device {
+            let is_pci_id = prefer_vk_device
+                .split_once(':')
+                .is_some_and(|(vendor, device)| {
+                    [vendor, device].iter().all(|id| {
+                        id.len() == 4 && id.chars().all(|character| character.is_ascii_hexdigit())
+                    })
+                });
+
+            if !is_pci_id {
+                findings.push(Finding {
+                    severity: DiagnosticSeverity::Error,
+                    subject: Subject::Field("prefer_vk_device"),
+                    message: format!("`prefer_vk_device` has to be a `vendor:device` PCI ID like `1002:73bf`, but it's `{prefer_vk_device}`"),
+                });
+            }
+        }
+
+        if gamescope.expose_wayland && gamescope.backend == GamescopeBackend::Auto {
+            findings.push(Finding {
+                severity: DiagnosticSeverity::Warning,
+                subject: Subject::Field("expose_wayland"),
+                message: String::from("`expose_wayland` only has an effect with the `Wayland` backend, but the backend is `Auto`"),
+            });
+        }
+
+        let gamescope_limits_framerate = gamescope.extra_arguments.iter().any(|argument| {
+            argument == "-r"
+                || argument == "--framerate-limit"
+                || argument.starts_with("--framerate-limit=")
+        });
+
+        if config.fps_limit > 0 && gamescope_limits_framerate {
+            findings.push(Finding {
+                severity: DiagnosticSeverity::Warning,
+                subject: Subject::Field("fps_limit"),
+                message: format!(
+                    "`fps_limit` ({}) is set and gamescope's `extra_arguments` limit the frame rate as well, it will be limited twice. Use only one of them",
+                    config.fps_limit
+                ),
+            });
+        }
+    }
+
+    for key in config.environment_variables.keys() {
+        if key.is_empty() {
+            findings.push(Finding {
+                severity: DiagnosticSeverity::Error,
+                subject: Subject::EnvironmentVariable(key.to_string()),
+                message: String::from("An environment variable has an empty name"),
+            });
+        } else if key.contains('=') || key.contains('\0') {
+            findings.push(Finding {
+                severity: DiagnosticSeverity::Error,
+                subject: Subject::EnvironmentVariable(key.to_string()),
+                message: format!(
+                    "The environment variable name `{key}` can't contain `=` or NUL characters"
+                ),
+            });
+        }
+    }
+
+    findings
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    String(String),
+    Punctuation(char),
+}
+
+// Walks through RON source the way the parser sees it, so that comments and the contents of
+// strings are skipped. Positions are 1-based lines and columns.
+struct Scanner<'a> {
+    characters: Peekable<Chars<'a>>,
+    line: u16,
+    column: u16,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            characters: source.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn next_character(&mut self) -> Option<char> {
+        let character = self.characters.next()?;
+
+        if character == '\n' {
+            self.line = self.line.saturating_add(1);
+            self.column = 1;
+        } else {
+            self.column = self.column.saturating_add(1);
+        }
+
+        Some(character)
+    }
+
+    fn next_character_if(&mut self, expected: char) -> bool {
+        if self.characters.peek() == Some(&expected) {
+            self.next_character();
+
+            return true;
+        }
+
+        false
+    }
+
+    // Block comments can be nested in RON.
+    fn skip_block_comment(&mut self) {
+        let mut depth = 1;
+
+        while depth > 0 {
+            match self.next_character() {
+                Some('*') if self.next_character_if('/') => depth -= 1,
+                Some('/') if self.next_character_if('*') => depth += 1,
+                Some(_) => {}
+                None => return,
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> String {
+        let mut string = String::new();
+
+        while let Some(character) = self.next_character() {
+            match character {
+                '"' => break,
+                '\\' => match self.next_character() {
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some('r') => string.push('\r'),
+                    Some('0') => string.push('\0'),
+                    Some(escaped) => string.push(escaped),
+                    None => break,
+                },
+                character => string.push(character),
+            }
+        }
+
+        string
+    }
+
+    // `r"..."` or `r#"..."#`, after the `r`.
+    fn read_raw_string(&mut self) -> String {
+        let mut hashes = 0;
+
+        while self.next_character_if('#') {
+            hashes += 1;
+        }
+
+        self.next_character_if('"');
+
+        let mut string = String::new();
+
+        while let Some(character) = self.next_character() {
+            if character == '"' {
+                let mut closing_hashes = 0;
+
+                while closing_hashes < hashes && self.next_character_if('#') {
+                    closing_hashes += 1;
+                }
+
+                if closing_hashes == hashes {
+                    break;
+                }
+
+                string.push('"');
+                string.push_str(&"#".repeat(closing_hashes));
+            } else {
+                string.push(character);
+            }
+        }
+
+        string
+    }
+}
+
+impl Iterator for Scanner<'_> {
+    type Item = (Token, (u16, u16));
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let position = (self.line, self.column);
+            let character = self.next_character()?;
+
+            let token = match character {
+                character if character.is_whitespace() => continue,
+                '/' if self.next_character_if('/') => {
+                    while !matches!(self.next_character(), Some('\n') | None) {}
+
+                    continue;
+                }
+                '/' if self.next_character_if('*') => {
+                    self.skip_block_comment();
+
+                    continue;
+                }
+                '"' => Token::String(self.read_string()),
+                'r' if matches!(self.characters.peek(), Some('"' | '#')) => {
+                    Token::String(self.read_raw_string())
+                }
+                character if character.is_alphabetic() || character == '_' => {
+                    let mut identifier = String::from(character);
+
+                    while let Some(&character) = self.characters.peek() {
+                        if !character.is_alphanumeric() && character != '_' {
+                            break;
+                        }
+
+                        identifier.push(character);
+                        self.next_character();
+                    }
+
+                    Token::Identifier(identifier)
+                }
+                character => Token::Punctuation(character),
+            };
+
+            return Some((token, position));
+        }
+    }
+}
+
+fn find_position(source: &str, subject: &Subject) -> Option<(u16, u16)> {
+    let tokens: Vec<(Token, (u16, u16))> = Scanner::new(source).collect();
+
+    let is_punctuation = |index: Option<usize>, expected: char| -> bool {
+        index
+            .and_then(|index| tokens.get(index))
+            .is_some_and(|(token, _)| *token == Token::Punctuation(expected))
+    };
+
+    tokens
+        .iter()
+        .enumerate()
+        .find(|(index, (token, _))| match (subject, token) {
+            (Subject::Field(field), Token::Identifier(identifier)) => {
+                identifier == field && is_punctuation(Some(index + 1), ':')
+            }
+            // The key of a map, or the first element of a `("KEY", "value")` tuple.
+            (Subject::EnvironmentVariable(key), Token::String(string)) => {
+                string == key
+                    && (is_punctuation(Some(index + 1), ':')
+                        || is_punctuation(index.checked_sub(1), '('))
+            }
+            _ => false,
+        })
+        .map(|(_, (_, position))| *position)
+}
+
+// Semantic problems in an already parsed config. If the sources of the files it was merged
+// from are given, the diagnostics point to the file that set the field they're about, the last
+// one wins like when merging.
+pub fn check_game_config(config: &GameConfig, sources: &[LayerSource]) -> Vec<Diagnostic> {
+    find_semantic_problems(config)
+        .into_iter()
+        .map(|finding| {
+            let origin = sources.iter().rev().find_map(|source| {
+                find_position(&source.contents, &finding.subject)
+                    .map(|position| (&source.path, position))
+            });
+
+            let (path, position) = match origin {
+                Some((path, position)) => (Some(path.clone()), Some(position)),
+                None => (sources.last().map(|source| source.path.clone()), None),
+            };
+
+            Diagnostic {
+                severity: finding.severity,
+                message: finding.message,
+                path,
+                position,
+            }
+        })
+        .collect()
+}
+
+// Parses the file on top of the underlying ones (e.g. defaults.ron) and checks the result.
+pub async fn validate_game_config_file(
+    mut file: GameConfigFile,
+    underlying_files: Vec<GameConfigFile>,
+) -> Vec<Diagnostic> {
+    let mut files = underlying_files;
+
+    files.push(file);
+
+    match GameConfig::from_game_config_files_with_sources(files).await {
+        Ok((config, sources)) => check_game_config(&config, &sources),
+        Err(error) => vec![Diagnostic::from(error)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_game_config_file, DiagnosticSeverity};
+    use crate::config::GameConfigFile;
+    use color_eyre::eyre;
+    use tempdir::TempDir;
+    use tokio::fs;
+
+    #[tokio::test]
+    async fn report_semantic_problems_with_positions() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("report_semantic_problems_with_positions")?;
+        let path = temp_dir.path().join("582660.ron");
+
+        fs::write(
+            &path,
+            r#"(
+    mangohud: true,
+    fps_limit: 60,
+    gamescope: Some((
+        mangoapp: true,
+        expose_wayland: true,
+        extra_arguments: ["--framerate-limit", "60"],
+    )),
+    environment_variables: [
+        ("DXVK_ASYNC", "1"),
+        ("BROKEN=KEY", "1"),
+    ],
+)"#,
+        )
+        .await?;
+
+        let diagnostics = validate_game_config_file(GameConfigFile { path }, vec![]).await;
+
+        let positions: Vec<(DiagnosticSeverity, Option<(u16, u16)>)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.position))
+            .collect();
+
+        assert_eq!(
+            positions,
+            vec![
+                (DiagnosticSeverity::Warning, Some((5, 9))),
+                (DiagnosticSeverity::Warning, Some((6, 9))),
+                (DiagnosticSeverity::Warning, Some((3, 5))),
+                (DiagnosticSeverity::Error, Some((11, 10))),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn attribute_problems_to_the_file_that_set_the_field() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("attribute_problems_to_the_file_that_set_the_field")?;
+        let defaults_path = temp_dir.path().join("defaults.ron");
+        let path = temp_dir.path().join("582660.ron");
+
+        fs::write(
+            &defaults_path,
+            "(\n    mangohud: false,\n    gamescope: Some((\n        expose_wayland: true,\n    )),\n)",
+        )
+        .await?;
+
+        fs::write(
+            &path,
+            r#"(
+    // expose_wayland: false, "BAD=KEY"
+    environment_variables: {"LD_LIBRARY_PATH": (prepend: "/opt/lib"), "BAD=KEY": "1"},
+)"#,
+        )
+        .await?;
+
+        let diagnostics = validate_game_config_file(
+            GameConfigFile { path: path.clone() },
+            vec![GameConfigFile {
+                path: defaults_path.clone(),
+            }],
+        )
+        .await;
+
+        let origins: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.path.as_deref(), diagnostic.position))
+            .collect();
+
+        assert_eq!(
+            origins,
+            vec![
+                (Some(defaults_path.as_path()), Some((4, 9))),
+                (Some(path.as_path()), Some((3, 71))),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn suggest_the_closest_field_for_unknown_fields() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("suggest_the_closest_field_for_unknown_fields")?;
+        let path = temp_dir.path().join("582660.ron");
+
+        fs::write(&path, "(\n    gamescope: Some((tearin: true)),\n)").await?;
+
+        let diagnostics = validate_game_config_file(GameConfigFile { path }, vec![]).await;
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].position.map(|(line, _)| line), Some(2));
+        assert!(
+            diagnostics[0]
+                .message
+                .contains("The closest valid field is `tearing`"),
+            "{}",
+            diagnostics[0].message
+        );
+
+        Ok(())
+    }
+}
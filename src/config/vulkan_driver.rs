@@ -1,19 +1,295 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+};
 
-#[derive(Deserialize, Debug, PartialEq, Default)]
+// Where the Vulkan loader looks for ICD manifests, `$XDG_DATA_DIRS` covers Nix profiles.
+const ICD_DIRECTORIES: [&str; 5] = [
+    "/etc/vulkan/icd.d",
+    "/usr/local/share/vulkan/icd.d",
+    "/usr/share/vulkan/icd.d",
+    "/run/opengl-driver/share/vulkan/icd.d",
+    "/run/opengl-driver-32/share/vulkan/icd.d",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum VulkanDriverError {
+    #[error("IO error while attempting to read the Vulkan ICD directory `{1:#?}`, see: {0:#?}")]
+    ReadIcdDirectory(io::Error, PathBuf),
+
+    #[error("Couldn't find a Vulkan ICD manifest for {0:?}, run `{} vulkan list` to see the installed drivers", env!("CARGO_PKG_NAME"))]
+    MissingIcd(VulkanDriver),
+
+    #[error("The Vulkan ICD manifest `{0}` doesn't exist")]
+    MissingCustomIcd(PathBuf),
+
+    #[error("The Vulkan ICD manifest paths can't be passed in an environment variable, see: {0}")]
+    JoinIcdPaths(env::JoinPathsError),
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone, Default)]
 pub enum VulkanDriver {
     #[default]
     Default,
     Amdvlk,
     Radv,
+    Nvidia,
+    Anv,
+    Nvk,
+    Lavapipe,
+    // Path to an ICD manifest, e.g. `Custom("/opt/mesa-git/share/vulkan/icd.d/radeon_icd.x86_64.json")`.
+    Custom(PathBuf),
 }
 
 impl VulkanDriver {
-    pub fn as_command(&self) -> Option<&str> {
+    // Based on the library the manifest points to, hasvk is anv for older Intel GPUs.
+    fn from_library_path(library_path: &Path) -> Option<Self> {
+        let library_name = library_path.file_name()?.to_str()?;
+
+        match library_name.split('.').next()? {
+            "amdvlk64" | "amdvlk32" => Some(Self::Amdvlk),
+            "libvulkan_radeon" => Some(Self::Radv),
+            "libGLX_nvidia" => Some(Self::Nvidia),
+            "libvulkan_intel" | "libvulkan_intel_hasvk" => Some(Self::Anv),
+            "libvulkan_nouveau" => Some(Self::Nvk),
+            "libvulkan_lvp" => Some(Self::Lavapipe),
+            _ => None,
+        }
+    }
+
+    // `AMD_VULKAN_ICD` picks between the two when both AMD drivers are installed.
+    fn amd_vulkan_icd(&self) -> Option<&str> {
         match self {
-            Self::Default => None,
-            Self::Amdvlk => Some("vk_amdvlk"),
-            Self::Radv => Some("vk_radv"),
+            Self::Amdvlk => Some("AMDVLK"),
+            Self::Radv => Some("RADV"),
+            _ => None,
+        }
+    }
+
+    // The manifests of every architecture are passed, so that 32-bit games work as well.
+    pub fn environment_variables(
+        &self,
+        manifests: &[IcdManifest],
+    ) -> Result<Vec<(String, String)>, VulkanDriverError> {
+        let manifest_paths: Vec<PathBuf> = match self {
+            Self::Default => return Ok(Vec::new()),
+            Self::Custom(path) if !path.is_file() => {
+                return Err(VulkanDriverError::MissingCustomIcd(path.clone()))
+            }
+            Self::Custom(path) => vec![path.clone()],
+            driver => manifests
+                .iter()
+                .filter(|manifest| manifest.driver.as_ref() == Some(driver))
+                .map(|manifest| manifest.path.clone())
+                .collect(),
+        };
+
+        if manifest_paths.is_empty() {
+            return Err(VulkanDriverError::MissingIcd(self.clone()));
+        }
+
+        let driver_files = env::join_paths(&manifest_paths)
+            .map_err(VulkanDriverError::JoinIcdPaths)?
+            .to_string_lossy()
+            .into_owned();
+
+        // `VK_ICD_FILENAMES` is the deprecated name, for Vulkan loaders older than 1.3.207.
+        let mut environment_variables = vec![
+            (String::from("VK_DRIVER_FILES"), driver_files.clone()),
+            (String::from("VK_ICD_FILENAMES"), driver_files),
+        ];
+
+        if let Some(amd_vulkan_icd) = self.amd_vulkan_icd() {
+            environment_variables
+                .push((String::from("AMD_VULKAN_ICD"), String::from(amd_vulkan_icd)));
+        }
+
+        Ok(environment_variables)
+    }
+}
+
+#[derive(Deserialize)]
+struct IcdManifestFile {
+    #[serde(rename = "ICD")]
+    icd: IcdManifestIcd,
+}
+
+#[derive(Deserialize)]
+struct IcdManifestIcd {
+    library_path: PathBuf,
+    api_version: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IcdManifest {
+    pub path: PathBuf,
+    pub library_path: PathBuf,
+    pub api_version: Option<String>,
+    // `None` for drivers that don't have a variant, they can still be used with `Custom`.
+    pub driver: Option<VulkanDriver>,
+}
+
+fn read_icd_manifest(path: &Path) -> Result<IcdManifestFile, String> {
+    let manifest = fs::read(path).map_err(|error| error.to_string())?;
+
+    serde_json::from_slice(&manifest).map_err(|error| error.to_string())
+}
+
+pub fn icd_directories() -> Vec<PathBuf> {
+    let mut directories: Vec<PathBuf> = ICD_DIRECTORIES.iter().map(PathBuf::from).collect();
+
+    if let Some(data_directories) = env::var_os("XDG_DATA_DIRS") {
+        directories.extend(
+            env::split_paths(&data_directories)
+                .map(|data_directory| data_directory.join("vulkan/icd.d")),
+        );
+    }
+
+    let mut unique_directories = Vec::new();
+
+    for directory in directories {
+        if !unique_directories.contains(&directory) {
+            unique_directories.push(directory);
+        }
+    }
+
+    unique_directories
+}
+
+// Manifests that can't be parsed are skipped, the Vulkan loader ignores them too.
+pub fn discover_icd_manifests(
+    directories: &[PathBuf],
+) -> Result<Vec<IcdManifest>, VulkanDriverError> {
+    let mut manifests = Vec::new();
+
+    for directory in directories {
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => {
+                return Err(VulkanDriverError::ReadIcdDirectory(
+                    error,
+                    directory.clone(),
+                ))
+            }
+        };
+
+        let mut paths = entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<PathBuf>>>()
+            .map_err(|error| VulkanDriverError::ReadIcdDirectory(error, directory.clone()))?;
+
+        paths.sort();
+
+        for path in paths {
+            if path.extension() != Some(OsStr::new("json")) {
+                continue;
+            }
+
+            let manifest = match read_icd_manifest(&path) {
+                Ok(manifest) => manifest,
+                Err(error) => {
+                    tracing::debug!(
+                        "Skipping the Vulkan ICD manifest `{}`. {error}",
+                        path.display()
+                    );
+
+                    continue;
+                }
+            };
+
+            manifests.push(IcdManifest {
+                driver: VulkanDriver::from_library_path(&manifest.icd.library_path),
+                library_path: manifest.icd.library_path,
+                api_version: manifest.icd.api_version,
+                path,
+            });
         }
     }
+
+    Ok(manifests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{discover_icd_manifests, VulkanDriver, VulkanDriverError};
+    use std::{fs, path::PathBuf};
+    use tempdir::TempDir;
+
+    #[test]
+    fn discover_manifests_and_select_drivers() {
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vulkan_icd");
+        let manifests = discover_icd_manifests(&[
+            fixtures.join("etc/vulkan/icd.d"),
+            fixtures.join("usr/share/vulkan/icd.d"),
+            fixtures.join("nix/vulkan/icd.d"),
+            fixtures.join("missing/vulkan/icd.d"),
+        ])
+        .expect("Failed to read the fixtures");
+
+        assert_eq!(
+            manifests
+                .iter()
+                .map(|manifest| manifest.driver.clone())
+                .collect::<Vec<_>>(),
+            [
+                Some(VulkanDriver::Amdvlk),
+                Some(VulkanDriver::Nvidia),
+                Some(VulkanDriver::Anv),
+                Some(VulkanDriver::Lavapipe),
+                Some(VulkanDriver::Radv),
+                Some(VulkanDriver::Radv),
+                Some(VulkanDriver::Nvk),
+            ]
+        );
+
+        let radv_files = format!(
+            "{}:{}",
+            fixtures
+                .join("usr/share/vulkan/icd.d/radeon_icd.i686.json")
+                .display(),
+            fixtures
+                .join("usr/share/vulkan/icd.d/radeon_icd.x86_64.json")
+                .display()
+        );
+
+        assert_eq!(
+            VulkanDriver::Radv
+                .environment_variables(&manifests)
+                .expect("Failed to select RADV"),
+            [
+                (String::from("VK_DRIVER_FILES"), radv_files.clone()),
+                (String::from("VK_ICD_FILENAMES"), radv_files),
+                (String::from("AMD_VULKAN_ICD"), String::from("RADV")),
+            ]
+        );
+
+        assert_eq!(
+            VulkanDriver::Default.environment_variables(&manifests).ok(),
+            Some(Vec::new())
+        );
+        assert!(matches!(
+            VulkanDriver::Nvk.environment_variables(&[]),
+            Err(VulkanDriverError::MissingIcd(VulkanDriver::Nvk))
+        ));
+        assert!(matches!(
+            VulkanDriver::Custom(fixtures.join("missing.json")).environment_variables(&manifests),
+            Err(VulkanDriverError::MissingCustomIcd(_))
+        ));
+
+        // `:` separates the paths, so it can't be part of one.
+        let temp_dir = TempDir::new("discover_manifests_and_select_drivers")
+            .expect("Failed to create a temp dir");
+        let manifest_with_colon = temp_dir.path().join("radeon:icd.json");
+
+        fs::write(&manifest_with_colon, "{}").expect("Failed to write the manifest");
+
+        assert!(matches!(
+            VulkanDriver::Custom(manifest_with_colon).environment_variables(&manifests),
+            Err(VulkanDriverError::JoinIcdPaths(_))
+        ));
+    }
 }
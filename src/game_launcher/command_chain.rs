@@ -0,0 +1,216 @@
+use crate::steam_launch_command::SteamLaunchCommand;
+use serde::Serialize;
+use std::fmt;
+
+// A single program in the chain, e.g. `strangle 90` or `gamescope -w 1920 -h 1080 --`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandLink {
+    pub program: String,
+    pub arguments: Vec<String>,
+}
+
+impl CommandLink {
+    pub fn new<S: ToString>(program: S) -> Self {
+        Self {
+            program: program.to_string(),
+            arguments: Vec::new(),
+        }
+    }
+
+    pub fn with_arguments<S, I, A>(program: S, arguments: I) -> Self
+    where
+        S: ToString,
+        I: IntoIterator<Item = A>,
+        A: ToString,
+    {
+        Self {
+            program: program.to_string(),
+            arguments: arguments
+                .into_iter()
+                .map(|argument| argument.to_string())
+                .collect(),
+        }
+    }
+
+    pub fn as_argv(&self) -> Vec<String> {
+        let mut argv = Vec::with_capacity(self.arguments.len() + 1);
+
+        argv.push(self.program.clone());
+        argv.extend(self.arguments.iter().cloned());
+
+        argv
+    }
+}
+
+// The wrappers are executed in the order they were pushed, each one receiving the rest
+// of the chain as its arguments. The chain always ends with Steam's own command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandChain {
+    wrappers: Vec<CommandLink>,
+    command: SteamLaunchCommand,
+}
+
+impl CommandChain {
+    pub fn new(command: SteamLaunchCommand) -> Self {
+        Self {
+            wrappers: Vec::new(),
+            command,
+        }
+    }
+
+    pub fn wrappers(&self) -> &[CommandLink] {
+        &self.wrappers
+    }
+
+    pub fn command(&self) -> &SteamLaunchCommand {
+        &self.command
+    }
+
+    // Runs the wrapper on the host, before Steam's reaper.
+    pub fn push_wrapper(&mut self, wrapper: CommandLink) {
+        self.wrappers.push(wrapper);
+    }
+
+    pub fn as_argv(&self) -> Vec<String> {
+        self.wrappers
+            .iter()
+            .flat_map(CommandLink::as_argv)
+            .chain(self.command.as_argv())
+            .collect()
+    }
+
+    pub fn as_std_command(&self) -> std::process::Command {
+        let argv = self.as_argv();
+        let (program, arguments) = argv
+            .split_first()
+            .expect("CommandChain should've always had at least one argument");
+
+        let mut command = std::process::Command::new(program);
+
+        command.args(arguments);
+
+        command
+    }
+}
+
+impl fmt::Display for CommandChain {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}", shell_words::join(self.as_argv()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandChain, CommandLink};
+    use crate::steam_launch_command::{SteamLaunchCommand, SteamLaunchCommandError};
+
+    const GAME_PATH_WITH_SPACES: &str =
+        "/home/user/.local/share/Steam/steamapps/common/Black Desert Online/BlackDesert64.exe";
+
+    const PROTON_PATH_WITH_SPACES: &str =
+        "/home/user/.local/share/Steam/steamapps/common/Proton - Experimental/proton";
+
+    fn steam_launch_command(game_arguments: &[&str]) -> SteamLaunchCommand {
+        let mut arguments: Vec<String> = [
+            "/home/user/.local/share/Steam/ubuntu12_32/reaper",
+            "SteamLaunch",
+            "AppId=582660",
+            "--",
+            "/home/user/.local/share/Steam/steamapps/common/SteamLinuxRuntime_sniper/_v2-entry-point",
+            "--verb=waitforexitandrun",
+            "--",
+            PROTON_PATH_WITH_SPACES,
+            "waitforexitandrun",
+            GAME_PATH_WITH_SPACES,
+        ]
+        .iter()
+        .map(|argument| argument.to_string())
+        .collect();
+
+        arguments.extend(game_arguments.iter().map(|argument| argument.to_string()));
+
+        SteamLaunchCommand::from_arguments(arguments).expect("Failed to create the Steam command")
+    }
+
+    #[test]
+    fn game_path_with_spaces_stays_a_single_argument() {
+        let command_string = format!(
+            "reaper SteamLaunch AppId=582660 -- '{PROTON_PATH_WITH_SPACES}' waitforexitandrun '{GAME_PATH_WITH_SPACES}' --use-d3d11"
+        );
+
+        let mut chain = CommandChain::new(
+            SteamLaunchCommand::parse(&command_string).expect("Failed to parse the Steam command"),
+        );
+
+        chain.push_wrapper(CommandLink::new("gamemoderun"));
+        chain.push_wrapper(CommandLink::with_arguments("strangle", ["90"]));
+
+        assert_eq!(
+            chain.as_argv(),
+            vec![
+                "gamemoderun",
+                "strangle",
+                "90",
+                "reaper",
+                "SteamLaunch",
+                "AppId=582660",
+                "--",
+                PROTON_PATH_WITH_SPACES,
+                "waitforexitandrun",
+                GAME_PATH_WITH_SPACES,
+                "--use-d3d11"
+            ]
+        );
+    }
+
+    #[test]
+    fn shell_metacharacters_are_passed_through_untouched() {
+        let game_arguments = [
+            "--profile=$HOME/profile",
+            "--name=\"quoted\" 'single'",
+            "$(rm -rf ~); `id` && echo | cat > /dev/null",
+            "*",
+        ];
+
+        let mut chain = CommandChain::new(steam_launch_command(&game_arguments));
+
+        chain.push_wrapper(CommandLink::with_arguments(
+            "gamescope",
+            ["-w", "1920", "-h", "1080", "--"],
+        ));
+
+        let command = chain.as_std_command();
+
+        assert_eq!(command.get_program(), "gamescope");
+
+        let passed_arguments: Vec<String> = command
+            .get_args()
+            .map(|argument| argument.to_string_lossy().to_string())
+            .collect();
+
+        assert_eq!(passed_arguments[..5], ["-w", "1920", "-h", "1080", "--"]);
+        assert!(passed_arguments.contains(&String::from(PROTON_PATH_WITH_SPACES)));
+        assert_eq!(
+            passed_arguments[passed_arguments.len() - game_arguments.len()..],
+            game_arguments
+        );
+    }
+
+    #[test]
+    fn display_quotes_arguments_that_need_it() {
+        let chain = CommandChain::new(steam_launch_command(&["$VAR"]));
+
+        assert!(chain
+            .to_string()
+            .ends_with(&format!("'{GAME_PATH_WITH_SPACES}' '$VAR'")));
+    }
+
+    // A chain can only be built from a parsed Steam command, which is never empty.
+    #[test]
+    fn empty_command_is_rejected() {
+        assert!(matches!(
+            SteamLaunchCommand::parse("   "),
+            Err(SteamLaunchCommandError::EmptyCommand)
+        ));
+    }
+}
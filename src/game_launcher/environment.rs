@@ -0,0 +1,227 @@
+use crate::config::EnvironmentOperation;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::{collections::HashMap, env, ffi::OsString};
+use tokio::process::Command;
+
+lazy_static! {
+    static ref VARIABLE_REFERENCE: Regex = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+}
+
+// The changes to the environment the game is launched with. They're applied to the spawned
+// command only, gtnkr's own environment stays untouched.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Environment {
+    operations: Vec<(String, EnvironmentOperation)>,
+}
+
+// `${VAR}` is replaced with the inherited value of `VAR`, or nothing if it isn't set. The
+// inherited values don't have to be UTF-8.
+fn expand<F>(value: &str, get_inherited: &F) -> OsString
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let mut expanded = OsString::new();
+    let mut end = 0;
+
+    for captures in VARIABLE_REFERENCE.captures_iter(value) {
+        let reference = captures.get(0).expect("The whole match is always captured");
+
+        expanded.push(&value[end..reference.start()]);
+
+        if let Some(inherited_value) = get_inherited(&captures[1]) {
+            expanded.push(inherited_value);
+        }
+
+        end = reference.end();
+    }
+
+    expanded.push(&value[end..]);
+
+    expanded
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<K: ToString>(&mut self, key: K, operation: EnvironmentOperation) {
+        self.operations.push((key.to_string(), operation));
+    }
+
+    pub fn set<K: ToString, V: ToString>(&mut self, key: K, value: V) {
+        self.push(key, EnvironmentOperation::Set(value.to_string()));
+    }
+
+    pub fn unset<K: ToString>(&mut self, key: K) {
+        self.push(key, EnvironmentOperation::Unset);
+    }
+
+    pub fn prepend<K: ToString, V: ToString>(&mut self, key: K, value: V, separator: &str) {
+        self.push(
+            key,
+            EnvironmentOperation::Prepend(value.to_string(), separator.to_string()),
+        );
+    }
+
+    pub fn append<K: ToString, V: ToString>(&mut self, key: K, value: V, separator: &str) {
+        self.push(
+            key,
+            EnvironmentOperation::Append(value.to_string(), separator.to_string()),
+        );
+    }
+
+    pub fn operations(&self) -> &[(String, EnvironmentOperation)] {
+        &self.operations
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    // The final value of every changed variable in the order they were first changed, `None`
+    // if the variable is removed.
+    pub fn resolve(&self) -> Vec<(String, Option<OsString>)> {
+        self.resolve_with(|key| env::var_os(key))
+    }
+
+    fn resolve_with<F>(&self, get_inherited: F) -> Vec<(String, Option<OsString>)>
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let mut keys: Vec<&str> = Vec::new();
+        let mut values: HashMap<&str, Option<OsString>> = HashMap::new();
+
+        for (key, operation) in &self.operations {
+            let current_value = match values.get(key.as_str()) {
+                Some(value) => value.clone(),
+                None => {
+                    keys.push(key);
+
+                    get_inherited(key)
+                }
+            };
+
+            let value = match operation {
+                EnvironmentOperation::Set(value) => Some(expand(value, &get_inherited)),
+                EnvironmentOperation::Unset => None,
+                EnvironmentOperation::Prepend(value, separator) => {
+                    let value = expand(value, &get_inherited);
+
+                    Some(match current_value.filter(|current| !current.is_empty()) {
+                        Some(current_value) => {
+                            let mut prepended = value;
+
+                            prepended.push(separator);
+                            prepended.push(current_value);
+
+                            prepended
+                        }
+                        None => value,
+                    })
+                }
+                EnvironmentOperation::Append(value, separator) => {
+                    let value = expand(value, &get_inherited);
+
+                    Some(match current_value.filter(|current| !current.is_empty()) {
+                        Some(mut appended) => {
+                            appended.push(separator);
+                            appended.push(value);
+
+                            appended
+                        }
+                        None => value,
+                    })
+                }
+            };
+
+            values.insert(key, value);
+        }
+
+        keys.into_iter()
+            .map(|key| (key.to_string(), values.remove(key).flatten()))
+            .collect()
+    }
+
+    pub fn apply(&self, command: &mut Command) {
+        for (key, value) in self.resolve() {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+    }
+}
+
+impl<K: ToString, V: ToString> Extend<(K, V)> for Environment {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, variables: I) {
+        for (key, value) in variables {
+            self.set(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Environment;
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+    #[test]
+    fn resolve_operations_against_the_inherited_environment() {
+        let inherited = |key: &str| match key {
+            "HOME" => Some(OsString::from("/home/user")),
+            "LD_PRELOAD" => Some(OsString::from("/usr/lib/libgamemodeauto.so.0")),
+            "DXVK_HUD" => Some(OsString::from("fps")),
+            "STEAM_COMPAT_DATA_PATH" => Some(OsString::from_vec(b"/games/\xff".to_vec())),
+            _ => None,
+        };
+
+        let mut environment = Environment::new();
+
+        environment.set("DXVK_CONFIG_FILE", "${HOME}/.config/dxvk.conf");
+        environment.set("WINEDLLOVERRIDES", "${MISSING}dxgi=n");
+        environment.prepend("LD_PRELOAD", "/opt/libfoo.so", ":");
+        environment.append("LD_PRELOAD", "/opt/libbar.so", ":");
+        environment.append("VK_LAYER_PATH", "/opt/layers", ":");
+        environment.unset("DXVK_HUD");
+        environment.set("WINEPREFIX", "${STEAM_COMPAT_DATA_PATH}/pfx");
+        environment.append("STEAM_COMPAT_DATA_PATH", "/pfx", "");
+        environment.set("PROTON_LOG", "1");
+        environment.unset("PROTON_LOG");
+
+        assert_eq!(
+            environment.resolve_with(inherited),
+            [
+                (
+                    String::from("DXVK_CONFIG_FILE"),
+                    Some(OsString::from("/home/user/.config/dxvk.conf"))
+                ),
+                (
+                    String::from("WINEDLLOVERRIDES"),
+                    Some(OsString::from("dxgi=n"))
+                ),
+                (
+                    String::from("LD_PRELOAD"),
+                    Some(OsString::from(
+                        "/opt/libfoo.so:/usr/lib/libgamemodeauto.so.0:/opt/libbar.so"
+                    ))
+                ),
+                (
+                    String::from("VK_LAYER_PATH"),
+                    Some(OsString::from("/opt/layers"))
+                ),
+                (String::from("DXVK_HUD"), None),
+                (
+                    String::from("WINEPREFIX"),
+                    Some(OsString::from_vec(b"/games/\xff/pfx".to_vec()))
+                ),
+                (
+                    String::from("STEAM_COMPAT_DATA_PATH"),
+                    Some(OsString::from_vec(b"/games/\xff/pfx".to_vec()))
+                ),
+                (String::from("PROTON_LOG"), None),
+            ]
+        );
+    }
+}
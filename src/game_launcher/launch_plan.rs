@@ -0,0 +1,331 @@
+use super::{CommandChain, CommandLink, Environment, GameLauncherError};
+use crate::{
+    config::{GameConfig, Gpu},
+    steam_launch_command::SteamLaunchCommand,
+};
+use serde::Serialize;
+use std::{
+    env,
+    fmt::{self, Write},
+    path::{Path, PathBuf},
+};
+use which::which;
+
+// Everything GameLauncher::launch_by_command would execute, without executing it.
+#[derive(Debug)]
+pub struct LaunchPlan {
+    pub game_identifier: String,
+    pub config_file: Option<PathBuf>,
+    pub command_chain: CommandChain,
+    pub environment: Environment,
+    // After merging the defaults and profiles.
+    pub config: GameConfig,
+    pub gpu: Option<Gpu>,
+    pub gamescope_version: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WrapperReport<'a> {
+    #[serde(flatten)]
+    link: &'a CommandLink,
+    resolved_path: Option<PathBuf>,
+}
+
+impl<'a> WrapperReport<'a> {
+    fn new(link: &'a CommandLink) -> Self {
+        Self {
+            link,
+            resolved_path: which(&link.program).ok(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EnvironmentVariableReport {
+    key: String,
+    // `None` if the variable is removed, values that aren't UTF-8 are shown lossily.
+    value: Option<String>,
+    inherited_value: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LaunchPlanReport<'a> {
+    game_identifier: &'a str,
+    config_file: Option<&'a Path>,
+    wrappers: Vec<WrapperReport<'a>>,
+    steam_launch_command: &'a SteamLaunchCommand,
+    environment_variables: Vec<EnvironmentVariableReport>,
+    argv: Vec<String>,
+}
+
+impl LaunchPlan {
+    fn as_report(&self) -> LaunchPlanReport<'_> {
+        let wrappers = self
+            .command_chain
+            .wrappers()
+            .iter()
+            .map(WrapperReport::new)
+            .collect();
+
+        let environment_variables = self
+            .environment
+            .resolve()
+            .into_iter()
+            .map(|(key, value)| EnvironmentVariableReport {
+                inherited_value: env::var_os(&key)
+                    .map(|inherited_value| inherited_value.to_string_lossy().into_owned()),
+                value: value.map(|value| value.to_string_lossy().into_owned()),
+                key,
+            })
+            .collect();
+
+        LaunchPlanReport {
+            game_identifier: &self.game_identifier,
+            config_file: self.config_file.as_deref(),
+            wrappers,
+            steam_launch_command: self.command_chain.command(),
+            environment_variables,
+            argv: self.command_chain.as_argv(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, GameLauncherError> {
+        serde_json::to_string_pretty(&self.as_report())
+            .map_err(GameLauncherError::SerializeLaunchPlan)
+    }
+}
+
+impl fmt::Display for LaunchPlan {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let report = self.as_report();
+
+        writeln!(formatter, "Game: {}", report.game_identifier)?;
+
+        match report.config_file {
+            Some(config_file) => writeln!(formatter, "Config file: {}", config_file.display())?,
+            None => writeln!(formatter, "Config file: none, using the defaults")?,
+        }
+
+        writeln!(formatter, "\nWrappers:")?;
+
+        if report.wrappers.is_empty() {
+            writeln!(formatter, "  none")?;
+        }
+
+        for wrapper in &report.wrappers {
+            let mut line = format!("  {}", wrapper.link.program);
+
+            match &wrapper.resolved_path {
+                Some(path) => write!(line, " ({})", path.display())?,
+                None => line.push_str(" (not found)"),
+            }
+
+            writeln!(formatter, "{line}")?;
+
+            if !wrapper.link.arguments.is_empty() {
+                writeln!(
+                    formatter,
+                    "    {}",
+                    shell_words::join(&wrapper.link.arguments)
+                )?;
+            }
+        }
+
+        let steam_launch_command = report.steam_launch_command;
+
+        writeln!(
+            formatter,
+            "\nSteam command (AppId {}):",
+            steam_launch_command.app_id
+        )?;
+
+        if let Some(runtime) = &steam_launch_command.runtime {
+            writeln!(formatter, "  Runtime: {}", runtime.entry_point)?;
+        }
+
+        if let Some(proton) = &steam_launch_command.proton {
+            writeln!(formatter, "  Proton: {} {}", proton.executable, proton.verb)?;
+        }
+
+        writeln!(
+            formatter,
+            "  Game: {}",
+            steam_launch_command.game_executable
+        )?;
+
+        if !steam_launch_command.game_arguments.is_empty() {
+            writeln!(
+                formatter,
+                "  Arguments: {}",
+                shell_words::join(&steam_launch_command.game_arguments)
+            )?;
+        }
+
+        writeln!(formatter, "\nEnvironment:")?;
+
+        if report.environment_variables.is_empty() {
+            writeln!(formatter, "  unchanged")?;
+        }
+
+        for variable in &report.environment_variables {
+            let inherited_value = match &variable.inherited_value {
+                Some(inherited_value) => format!("was {}", shell_words::quote(inherited_value)),
+                None => String::from("was unset"),
+            };
+
+            match &variable.value {
+                Some(value) => writeln!(
+                    formatter,
+                    "  {}={} ({inherited_value})",
+                    variable.key,
+                    shell_words::quote(value)
+                )?,
+                None => writeln!(formatter, "  {} removed ({inherited_value})", variable.key)?,
+            }
+        }
+
+        writeln!(formatter, "\nCommand:")?;
+        write!(formatter, "  {}", self.command_chain)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::LaunchPlan;
+    use crate::{
+        config::GameConfig,
+        game_launcher::{CommandChain, CommandLink, Environment},
+        steam_launch_command::SteamLaunchCommand,
+    };
+    use color_eyre::eyre;
+    use std::path::PathBuf;
+
+    // Also used by the tests of the other launcher modules.
+    pub(in crate::game_launcher) fn launch_plan() -> eyre::Result<LaunchPlan> {
+        let steam_launch_command = SteamLaunchCommand::parse(include_str!(
+            "../../tests/fixtures/steam_launch_commands/native_without_runtime.txt"
+        ))?;
+
+        let mut command_chain = CommandChain::new(steam_launch_command);
+
+        command_chain.push_wrapper(CommandLink::with_arguments(
+            "gtnkr-missing-wrapper",
+            ["--fullscreen", "--"],
+        ));
+        command_chain.push_wrapper(CommandLink::new("gtnkr-missing-second-wrapper"));
+
+        let mut environment = Environment::new();
+
+        environment.set("GTNKR_LAUNCH_PLAN_TEST", "a b");
+        environment.unset("GTNKR_LAUNCH_PLAN_TEST_REMOVED");
+
+        Ok(LaunchPlan {
+            game_identifier: String::from("1145360"),
+            config_file: Some(PathBuf::from(
+                "/home/user/.config/gtnkr/game_configs/1145360.ron",
+            )),
+            command_chain,
+            environment,
+            config: GameConfig::default(),
+            gpu: None,
+            gamescope_version: None,
+        })
+    }
+
+    #[test]
+    fn display_and_serialize_a_launch_plan() -> eyre::Result<()> {
+        let launch_plan = launch_plan()?;
+
+        assert_eq!(
+            launch_plan.to_string(),
+            concat!(
+                "Game: 1145360\n",
+                "Config file: /home/user/.config/gtnkr/game_configs/1145360.ron\n",
+                "\n",
+                "Wrappers:\n",
+                "  gtnkr-missing-wrapper (not found)\n",
+                "    --fullscreen --\n",
+                "  gtnkr-missing-second-wrapper (not found)\n",
+                "\n",
+                "Steam command (AppId 1145360):\n",
+                "  Game: /home/user/.local/share/Steam/steamapps/common/Hades/Hades\n",
+                "\n",
+                "Environment:\n",
+                "  GTNKR_LAUNCH_PLAN_TEST='a b' (was unset)\n",
+                "  GTNKR_LAUNCH_PLAN_TEST_REMOVED removed (was unset)\n",
+                "\n",
+                "Command:\n",
+                "  gtnkr-missing-wrapper --fullscreen -- gtnkr-missing-second-wrapper ",
+                "/home/user/.local/share/Steam/ubuntu12_32/reaper SteamLaunch 'AppId=1145360' -- ",
+                "/home/user/.local/share/Steam/ubuntu12_32/steam-launch-wrapper -- ",
+                "/home/user/.local/share/Steam/steamapps/common/Hades/Hades",
+            )
+        );
+
+        let report: serde_json::Value = serde_json::from_str(&launch_plan.to_json()?)?;
+
+        assert_eq!(
+            report,
+            serde_json::json!({
+                "game_identifier": "1145360",
+                "config_file": "/home/user/.config/gtnkr/game_configs/1145360.ron",
+                "wrappers": [
+                    {
+                        "program": "gtnkr-missing-wrapper",
+                        "arguments": ["--fullscreen", "--"],
+                        "resolved_path": null,
+                    },
+                    {
+                        "program": "gtnkr-missing-second-wrapper",
+                        "arguments": [],
+                        "resolved_path": null,
+                    },
+                ],
+                "steam_launch_command": {
+                    "app_id": 1145360,
+                    "reaper": [
+                        "/home/user/.local/share/Steam/ubuntu12_32/reaper",
+                        "SteamLaunch",
+                        "AppId=1145360",
+                        "--",
+                    ],
+                    "launch_wrapper": [
+                        "/home/user/.local/share/Steam/ubuntu12_32/steam-launch-wrapper",
+                        "--",
+                    ],
+                    "runtime": null,
+                    "proton": null,
+                    "game_executable": "/home/user/.local/share/Steam/steamapps/common/Hades/Hades",
+                    "game_arguments": [],
+                },
+                "environment_variables": [
+                    {
+                        "key": "GTNKR_LAUNCH_PLAN_TEST",
+                        "value": "a b",
+                        "inherited_value": null,
+                    },
+                    {
+                        "key": "GTNKR_LAUNCH_PLAN_TEST_REMOVED",
+                        "value": null,
+                        "inherited_value": null,
+                    },
+                ],
+                "argv": [
+                    "gtnkr-missing-wrapper",
+                    "--fullscreen",
+                    "--",
+                    "gtnkr-missing-second-wrapper",
+                    "/home/user/.local/share/Steam/ubuntu12_32/reaper",
+                    "SteamLaunch",
+                    "AppId=1145360",
+                    "--",
+                    "/home/user/.local/share/Steam/ubuntu12_32/steam-launch-wrapper",
+                    "--",
+                    "/home/user/.local/share/Steam/steamapps/common/Hades/Hades",
+                ],
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -1,11 +1,47 @@
-use crate::config::{GameConfig, GameConfigError, GameConfigFile};
+mod command_chain;
+mod environment;
+mod launch_plan;
+mod output_capture;
+mod session_metadata;
+
+pub use crate::config::EnvironmentOperation;
+pub use command_chain::{CommandChain, CommandLink};
+pub use environment::Environment;
+pub use launch_plan::LaunchPlan;
+pub use output_capture::{OutputCapture, OutputLogFiles, OutputStream, OutputTee};
+
+use crate::config::{
+    check_game_config, discover_icd_manifests, icd_directories, Diagnostic, GameConfig,
+    GameConfigError, GameConfigFile, GamescopeHelp, GpuError, LogRetention, VulkanDriver,
+    VulkanDriverError,
+};
 use crate::process_output_log::{
-    ActiveOutputLog, PersistentOutputLog, ProcessOutputLog, ProcessOutputLogError,
-    ProcessOutputLogKind,
+    persistent_process_output_log_directory, prune_sessions, ActiveOutputLog, PersistentOutputLog,
+    ProcessOutputLog, ProcessOutputLogError, ProcessOutputLogKind,
+    RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY,
+};
+use crate::resolution_provider::ResolutionProviderError;
+use crate::steam_launch_command::SteamLaunchCommand;
+use chrono::Local;
+use nix::{
+    sys::signal::{self, killpg, SigHandler, Signal},
+    unistd::Pid,
 };
 use phf::phf_map;
-use std::{env, path::PathBuf};
-use tokio::{io, process::Command};
+use session_metadata::{collect_session_metadata, write_session_metadata_files};
+use std::{
+    env,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::{Path, PathBuf},
+    process::{self, ExitStatus, Stdio},
+    time::Instant,
+};
+use tokio::{
+    fs::File,
+    io,
+    process::{Child, Command},
+    signal::unix::{signal, SignalKind},
+};
 use which::which;
 
 #[derive(Debug, thiserror::Error)]
@@ -22,117 +58,434 @@ pub enum GameLauncherError {
     #[error(transparent)]
     ParseConfigFile(GameConfigError),
 
+    #[error("The game config is invalid. {0}")]
+    InvalidGameConfig(Diagnostic),
+
+    #[error("Failed to detect the native screen resolution for gamescope. Set `source_resolution` to `Custom(width, height)`, or set `resolution_fallback` to `Custom(width, height)` or `Gamescope` to launch anyway. See: {0}")]
+    DetectScreenResolution(ResolutionProviderError),
+
+    #[error("The installed gamescope ({1}) doesn't support the `{0}` flag. Update gamescope, or remove the option or extra argument that adds it from the game config")]
+    UnsupportedGamescopeFlag(String, String),
+
+    #[error("Failed to select the GPU from the game config's `gpu`. {0}")]
+    SelectGpu(GpuError),
+
+    #[error("Failed to select the Vulkan driver from the game config's `vulkan_driver`. {0}")]
+    SelectVulkanDriver(VulkanDriverError),
+
     #[error(r#"Failed to locate the cli tool "{0}", do you have {1} installed?"#)]
     MissingCliTool(String, String),
 
     #[error("Failed to run the launch or gamescope command, see: {0:#?}")]
     RunCommand(io::Error),
 
+    #[error("Failed to wait for the game's process to exit, see: {0:#?}")]
+    WaitForProcess(io::Error),
+
+    #[error("Failed to listen for signals to forward to the game, see: {0:#?}")]
+    ListenForSignals(io::Error),
+
     #[error(transparent)]
     ProcessOutputLog(ProcessOutputLogError),
+
+    #[error("Failed to serialize the launch plan into JSON, see: {0}")]
+    SerializeLaunchPlan(serde_json::Error),
+}
+
+#[derive(Debug, Default)]
+pub struct LaunchOptions {
+    // Keep the output logs in the home directory after the game exited.
+    pub persistent_output_log: bool,
+    pub combined_output_log: bool,
+    pub tee: Option<OutputTee>,
 }
 
 pub struct GameLauncher {}
 
 impl GameLauncher {
-    pub async fn launch_by_command(
-        command: &str,
+    pub async fn plan(
+        command: &SteamLaunchCommand,
         game_identifier: &str,
-        persistent_output_log: bool,
-    ) -> Result<(), GameLauncherError> {
+    ) -> Result<LaunchPlan, GameLauncherError> {
         let config_file = GameConfigFile::from_filename(game_identifier)
             .await
             .map_err(GameLauncherError::FindConfigFile)?;
 
-        let config = {
-            if let Some(config_file) = config_file {
-                GameConfig::from_game_config_file(config_file)
-                    .await
-                    .map_err(GameLauncherError::ParseConfigFile)?
-            } else {
-                tracing::warn!("Game config file with the name `{game_identifier}` doesn't exist, using the defaults.");
+        let config_file_path = config_file.as_ref().map(|file| file.path.clone());
 
-                GameConfig::default()
+        if config_file.is_none() {
+            tracing::warn!("Game config file with the name `{game_identifier}` doesn't exist, using the defaults.");
+        }
+
+        let config = GameConfig::load(config_file)
+            .await
+            .map_err(GameLauncherError::ParseConfigFile)?;
+
+        Self::plan_with_config(command, game_identifier, config_file_path, config).await
+    }
+
+    // Missing wrappers are left in the plan as they are, so that a dry run shows them as not
+    // found. Launching checks for them.
+    async fn plan_with_config(
+        command: &SteamLaunchCommand,
+        game_identifier: &str,
+        config_file_path: Option<PathBuf>,
+        config: GameConfig,
+    ) -> Result<LaunchPlan, GameLauncherError> {
+        for diagnostic in check_game_config(&config, &[]) {
+            if diagnostic.is_error() {
+                return Err(GameLauncherError::InvalidGameConfig(diagnostic));
             }
-        };
 
-        let mut launch_command: Vec<String> = Vec::new();
+            tracing::warn!("{}", diagnostic.message);
+        }
+
+        let mut launch_command = CommandChain::new(command.clone());
 
         if config.gamemode {
-            launch_command.push(find_executable_gml("gamemoderun")?);
+            launch_command.push_wrapper(CommandLink::new("gamemoderun"));
         }
 
         if config.mangohud {
-            launch_command.push(find_executable_gml("mangohud")?);
+            launch_command.push_wrapper(CommandLink::new("mangohud"));
         }
 
-        if let Some(gamescope_config) = config.gamescope {
-            if let Ok(gamescope_path) = env::var(format!(
+        // Variables required by the wrappers come first, so that the config can override them.
+        let mut environment = Environment::new();
+
+        let gpu = config
+            .gpu
+            .as_ref()
+            .map(|gpu_selector| gpu_selector.select())
+            .transpose()
+            .map_err(GameLauncherError::SelectGpu)?;
+
+        if let Some(gpu) = &gpu {
+            tracing::info!("Running the game on the GPU {gpu}");
+
+            environment.extend(gpu.environment_variables());
+        }
+
+        let icd_manifests = match config.vulkan_driver {
+            VulkanDriver::Default | VulkanDriver::Custom(_) => Vec::new(),
+            _ => discover_icd_manifests(&icd_directories())
+                .map_err(GameLauncherError::SelectVulkanDriver)?,
+        };
+
+        environment.extend(
+            config
+                .vulkan_driver
+                .environment_variables(&icd_manifests)
+                .map_err(GameLauncherError::SelectVulkanDriver)?,
+        );
+
+        let mut gamescope_version = None;
+
+        if let Some(mut gamescope_config) = config.gamescope.clone() {
+            // gamescope should composite on the GPU the game renders on.
+            if let (Some(gpu), None) = (&gpu, &gamescope_config.prefer_vk_device) {
+                gamescope_config.prefer_vk_device = Some(gpu.pci_id());
+            }
+
+            if let Some(reason) = gamescope_config.hdr_unsupported_reason() {
+                tracing::warn!("HDR is enabled, but it probably won't work. {reason}.");
+            }
+
+            environment.extend(gamescope_config.environment_variables());
+
+            let gamescope_path = env::var(format!(
                 "{}_GAMESCOPE_PATH",
                 crate::UPPERCASE_PACKAGE_NAME.as_str()
-            )) {
-                launch_command.push(gamescope_config.as_command(&gamescope_path));
-            } else {
-                launch_command
-                    .push(gamescope_config.as_command(&find_executable_gml("gamescope")?));
+            ))
+            .unwrap_or_else(|_| String::from("gamescope"));
+
+            let gamescope_help = GamescopeHelp::probe(&gamescope_path).await;
+
+            gamescope_version = gamescope_help.version().map(String::from);
+
+            // The resolution is looked up over blocking IPC and commands, each with a timeout.
+            let gamescope_command = {
+                let gamescope_help = gamescope_help.clone();
+
+                tokio::task::spawn_blocking(move || {
+                    gamescope_config.as_command(&gamescope_path, &gamescope_help)
+                })
+                .await
+                .expect("Looking up the screen resolution shouldn't panic")
+                .map_err(GameLauncherError::DetectScreenResolution)?
+            };
+
+            if let Some(flag) = gamescope_help.find_unsupported_flag(&gamescope_command.arguments) {
+                return Err(GameLauncherError::UnsupportedGamescopeFlag(
+                    flag.to_string(),
+                    gamescope_help
+                        .version()
+                        .map_or_else(|| String::from("unknown version"), String::from),
+                ));
             }
+
+            launch_command.push_wrapper(gamescope_command);
         }
 
         if config.fps_limit > 0 {
-            launch_command.push(format!(
-                "{} {}",
-                find_executable_gml("strangle")?,
-                config.fps_limit
-            ));
+            launch_command
+                .push_wrapper(CommandLink::with_arguments("strangle", [config.fps_limit]));
         }
 
-        if let Some(vulkan_driver) = config.vulkan_driver.as_command() {
-            launch_command.push(find_executable_gml(vulkan_driver)?);
+        for (key, operation) in config.environment_variables.iter().cloned() {
+            environment.push(key, operation);
         }
 
-        config
-            .environment_variables
-            .iter()
-            .for_each(|(key, value)| env::set_var(key, value));
+        Ok(LaunchPlan {
+            game_identifier: game_identifier.to_string(),
+            config_file: config_file_path,
+            command_chain: launch_command,
+            environment,
+            config,
+            gpu,
+            gamescope_version,
+        })
+    }
+
+    pub async fn launch_by_command(
+        command: &SteamLaunchCommand,
+        game_identifier: &str,
+        options: LaunchOptions,
+    ) -> Result<ExitStatus, GameLauncherError> {
+        let plan = Self::plan(command, game_identifier).await?;
+
+        find_wrapper_executables(&plan.command_chain)?;
+
+        let launch_command = &plan.command_chain;
+
+        tracing::info!("Launching the game with [{launch_command}]");
+
+        let started_at = Local::now();
+        let started = Instant::now();
+        let session_timestamp = started_at.naive_local();
+        let create_output_log =
+            |kind| ActiveOutputLog::create_at(game_identifier, kind, session_timestamp);
+
+        let stdout_log = create_output_log(ProcessOutputLogKind::Stdout);
+        let stderr_log = create_output_log(ProcessOutputLogKind::Stderr);
+        let combined_log = options
+            .combined_output_log
+            .then(|| create_output_log(ProcessOutputLogKind::Combined));
+
+        let open_output_log = |output_log: &ActiveOutputLog| {
+            output_log
+                .as_output_file()
+                .map(File::from_std)
+                .map_err(GameLauncherError::ProcessOutputLog)
+        };
+
+        let output_log_files = OutputLogFiles {
+            stdout: open_output_log(&stdout_log)?,
+            stderr: open_output_log(&stderr_log)?,
+            combined: combined_log.as_ref().map(open_output_log).transpose()?,
+        };
+
+        let mut std_command = launch_command.as_std_command();
 
-        let launch_command_string = format!("{} {}", launch_command.join(" "), command);
+        // The game gets its own process group, so that signals reach every wrapper and the game.
+        std_command.process_group(0);
 
-        tracing::info!("Launching the game with [{launch_command_string}]");
+        let mut command = Command::from(std_command);
 
-        let active_stderr_output_log =
-            ActiveOutputLog::create(game_identifier, ProcessOutputLogKind::Stderr)
-                .map_err(GameLauncherError::ProcessOutputLog)?;
+        plan.environment.apply(&mut command);
 
-        let mut process = Command::new("/bin/sh")
-            .arg("-c")
-            .arg(launch_command_string)
-            .stderr(
-                active_stderr_output_log
-                    .as_stdio()
-                    .map_err(GameLauncherError::ProcessOutputLog)?,
-            )
+        // Listening before the game starts, a signal that arrives in between is forwarded too.
+        let forwarded_signals = ForwardedSignals::listen()?;
+
+        let mut process = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()
             .map_err(GameLauncherError::RunCommand)?;
 
-        let _ = process.wait().await;
+        let output_capture = OutputCapture::start(
+            process
+                .stdout
+                .take()
+                .expect("The game's stdout should've been piped"),
+            process
+                .stderr
+                .take()
+                .expect("The game's stderr should've been piped"),
+            output_log_files,
+            options.tee,
+        );
+
+        let exit_status = wait_forwarding_signals(&mut process, forwarded_signals).await?;
+        let ended_at = Local::now();
+        let duration = started.elapsed();
 
-        if persistent_output_log {
-            PersistentOutputLog::from_active_output_log(active_stderr_output_log)
-                .map_err(GameLauncherError::ProcessOutputLog)?;
+        if !exit_status.success() {
+            tracing::warn!("The game exited with {exit_status}");
         }
 
-        Ok(())
+        // The game already ran, failing to log it only warns so that gtnkr still exits with
+        // the game's exit status.
+        if let Err(error) = output_capture.finish().await {
+            tracing::warn!("Failed to capture the game's output. {error}");
+        }
+
+        let output_logs: Vec<ActiveOutputLog> = [Some(stdout_log), Some(stderr_log), combined_log]
+            .into_iter()
+            .flatten()
+            .collect();
+
+        if options.persistent_output_log {
+            for output_log in &output_logs {
+                if let Err(error) = PersistentOutputLog::from_active_output_log(output_log) {
+                    tracing::warn!("Failed to keep the output log. {error}");
+                }
+            }
+        }
+
+        let session_metadata =
+            collect_session_metadata(&plan, started_at, ended_at, duration, exit_status).await;
+
+        let written_metadata = options
+            .persistent_output_log
+            .then(persistent_process_output_log_directory)
+            .transpose()
+            .and_then(|persistent_log_directory| {
+                write_session_metadata_files(
+                    &session_metadata,
+                    &session_timestamp,
+                    &RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY.join(game_identifier),
+                    persistent_log_directory
+                        .map(|directory| directory.join(game_identifier))
+                        .as_deref(),
+                )
+            });
+
+        if let Err(error) = written_metadata {
+            tracing::warn!("Failed to write the session metadata. {error}");
+        }
+
+        if options.persistent_output_log {
+            prune_persistent_output_logs(game_identifier, plan.config.log_retention.clone()).await;
+        }
+
+        remove_active_output_logs(output_logs);
+
+        Ok(exit_status)
     }
 }
 
+// The session metadata is left next to them, so that a launch without `--log-output` keeps it
+// until the runtime directory is cleared.
+fn remove_active_output_logs(output_logs: Vec<ActiveOutputLog>) {
+    for output_log in output_logs {
+        if let Err(error) = output_log.remove() {
+            tracing::warn!("Failed to remove the active output log. {error}");
+        }
+    }
+}
+
+// Failing to prune doesn't fail the launch, the logs are pruned again after the next one.
+async fn prune_persistent_output_logs(game_identifier: &str, log_retention: LogRetention) {
+    let game_log_directory = match persistent_process_output_log_directory() {
+        Ok(directory) => directory.join(game_identifier),
+        Err(error) => {
+            tracing::warn!("Failed to prune the output logs. {error}");
+
+            return;
+        }
+    };
+
+    let prune = tokio::task::spawn_blocking(move || {
+        prune_sessions(
+            &game_log_directory,
+            &log_retention,
+            Local::now().naive_local(),
+        )
+    });
+
+    match prune.await {
+        Ok(Ok(report)) => tracing::debug!("Pruned the output logs: {report:?}"),
+        Ok(Err(error)) => tracing::warn!("Failed to prune the output logs. {error}"),
+        Err(error) => tracing::warn!("Failed to prune the output logs. {error}"),
+    }
+}
+
+// SIGINT, SIGTERM and SIGHUP are passed on to the game's process group instead of stopping
+// gtnkr, the game decides whether it exits.
+struct ForwardedSignals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+    hangup: tokio::signal::unix::Signal,
+}
+
+impl ForwardedSignals {
+    fn listen() -> Result<Self, GameLauncherError> {
+        let listen = |kind| signal(kind).map_err(GameLauncherError::ListenForSignals);
+
+        Ok(Self {
+            interrupt: listen(SignalKind::interrupt())?,
+            terminate: listen(SignalKind::terminate())?,
+            hangup: listen(SignalKind::hangup())?,
+        })
+    }
+}
+
+async fn wait_forwarding_signals(
+    process: &mut Child,
+    mut signals: ForwardedSignals,
+) -> Result<ExitStatus, GameLauncherError> {
+    loop {
+        let received_signal = tokio::select! {
+            exit_status = process.wait() => {
+                return exit_status.map_err(GameLauncherError::WaitForProcess);
+            }
+            _ = signals.interrupt.recv() => Signal::SIGINT,
+            _ = signals.terminate.recv() => Signal::SIGTERM,
+            _ = signals.hangup.recv() => Signal::SIGHUP,
+        };
+
+        let Some(process_group_id) = process.id() else {
+            continue;
+        };
+
+        tracing::debug!("Forwarding {received_signal} to the game");
+
+        if let Err(error) = killpg(Pid::from_raw(process_group_id as i32), received_signal) {
+            tracing::debug!("Failed to forward {received_signal} to the game. {error}");
+        }
+    }
+}
+
+// Exits gtnkr the way the game exited: with the same exit code, or by the same signal.
+pub fn exit_like(exit_status: ExitStatus) -> ! {
+    if let Some(exit_code) = exit_status.code() {
+        process::exit(exit_code);
+    }
+
+    if let Some(terminating_signal) = exit_status
+        .signal()
+        .and_then(|signal_number| Signal::try_from(signal_number).ok())
+    {
+        // Tokio's handlers are still installed for the forwarded signals.
+        // SAFETY: No other handler is installed while this runs, gtnkr exits right after.
+        unsafe {
+            let _ = signal::signal(terminating_signal, SigHandler::SigDfl);
+        }
+
+        let _ = signal::raise(terminating_signal);
+
+        process::exit(128 + terminating_signal as i32);
+    }
+
+    process::exit(1);
+}
+
 static CLI_TOOL_INFO: phf::Map<&'static str, &'static str> = phf_map! {
     "gamemoderun" => "[gamemode](https://github.com/FeralInteractive/gamemode)",
     "mangohud" => "[MangoHud](https://github.com/flightlessmango/MangoHud)",
     "gamescope" => "[gamescope](https://github.com/ValveSoftware/gamescope)",
     "libstrangle" => "[libstrangle](https://github.com/milaq/libstrangle)",
-
-    "vk_amdvlk" => "[amd-vulkan-prefixes](https://gitlab.com/AndrewShark/amd-vulkan-prefixes)",
-    "vk_radv" => "[amd-vulkan-prefixes](https://gitlab.com/AndrewShark/amd-vulkan-prefixes)",
 };
 
 fn find_executable_gml(name: &str) -> Result<String, GameLauncherError> {
@@ -143,7 +496,12 @@ fn find_executable_gml(name: &str) -> Result<String, GameLauncherError> {
         return Ok(name.to_string()); // We don't need to use the full path if it's already on $PATH
     }
 
-    if let Some(pkg_name) = CLI_TOOL_INFO.get(name) {
+    let file_name = Path::new(name)
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .unwrap_or(name);
+
+    if let Some(pkg_name) = CLI_TOOL_INFO.get(file_name) {
         return Err(GameLauncherError::MissingCliTool(
             name.to_string(),
             pkg_name.to_string(),
@@ -155,3 +513,127 @@ fn find_executable_gml(name: &str) -> Result<String, GameLauncherError> {
         String::from("<[Undefined, please create an issue]>"),
     ))
 }
+
+fn find_wrapper_executables(command_chain: &CommandChain) -> Result<(), GameLauncherError> {
+    for wrapper in command_chain.wrappers() {
+        find_executable_gml(&wrapper.program)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        find_wrapper_executables, wait_forwarding_signals, ForwardedSignals, GameLauncher,
+        GameLauncherError,
+    };
+    use crate::{
+        config::{GameConfig, Gamescope, ScreenResolution},
+        steam_launch_command::SteamLaunchCommand,
+        UPPERCASE_PACKAGE_NAME,
+    };
+    use color_eyre::eyre;
+    use nix::{
+        sys::signal::{kill, Signal},
+        unistd::Pid,
+    };
+    use std::{
+        env,
+        os::unix::process::{CommandExt, ExitStatusExt},
+    };
+    use tokio::process::Command;
+
+    fn listen() -> ForwardedSignals {
+        ForwardedSignals::listen().expect("Failed to listen for signals")
+    }
+
+    #[tokio::test]
+    async fn report_the_exit_code_and_the_terminating_signal() {
+        let mut exiting_process = Command::new("sh")
+            .args(["-c", "exit 7"])
+            .spawn()
+            .expect("Failed to spawn sh");
+
+        let exit_status = wait_forwarding_signals(&mut exiting_process, listen())
+            .await
+            .expect("Failed to wait for sh");
+
+        assert_eq!(exit_status.code(), Some(7));
+
+        let mut killed_process = Command::new("sh")
+            .args(["-c", "kill -TERM $$"])
+            .spawn()
+            .expect("Failed to spawn sh");
+
+        let exit_status = wait_forwarding_signals(&mut killed_process, listen())
+            .await
+            .expect("Failed to wait for sh");
+
+        assert_eq!(exit_status.code(), None);
+        assert_eq!(exit_status.signal(), Some(15));
+    }
+
+    #[tokio::test]
+    async fn forward_signals_to_the_process_group() {
+        let signals = listen();
+        let mut std_command = std::process::Command::new("sleep");
+
+        std_command.arg("30").process_group(0);
+
+        let mut sleeping_process = Command::from(std_command)
+            .spawn()
+            .expect("Failed to spawn sleep");
+
+        // Sent before waiting, it's only forwarded because the signals were listened for first.
+        kill(Pid::this(), Signal::SIGTERM).expect("Failed to send SIGTERM");
+
+        let exit_status = wait_forwarding_signals(&mut sleeping_process, signals)
+            .await
+            .expect("Failed to wait for sleep");
+
+        assert_eq!(exit_status.signal(), Some(Signal::SIGTERM as i32));
+    }
+
+    #[tokio::test]
+    async fn plan_a_launch_with_a_missing_wrapper() -> eyre::Result<()> {
+        let gamescope_path = "/nonexistent/gtnkr-missing-gamescope";
+
+        env::set_var(
+            format!("{}_GAMESCOPE_PATH", UPPERCASE_PACKAGE_NAME.as_str()),
+            gamescope_path,
+        );
+
+        let config = GameConfig {
+            gamemode: false,
+            mangohud: false,
+            fps_limit: 0,
+            gamescope: Some(Gamescope {
+                source_resolution: ScreenResolution::Custom(1920, 1080),
+                ..Gamescope::default()
+            }),
+            ..GameConfig::default()
+        };
+
+        let plan = GameLauncher::plan_with_config(
+            &SteamLaunchCommand::parse(include_str!(
+                "../../tests/fixtures/steam_launch_commands/native_without_runtime.txt"
+            ))?,
+            "1145360",
+            None,
+            config,
+        )
+        .await?;
+
+        // A dry run still shows the plan, only launching fails.
+        assert!(plan
+            .to_string()
+            .contains(&format!("  {gamescope_path} (not found)\n")));
+        assert!(matches!(
+            find_wrapper_executables(&plan.command_chain),
+            Err(GameLauncherError::MissingCliTool(program, _)) if program == gamescope_path
+        ));
+
+        Ok(())
+    }
+}
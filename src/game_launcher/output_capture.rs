@@ -0,0 +1,589 @@
+use regex::Regex;
+use std::{env, io::IsTerminal, time::Duration};
+use tokio::{
+    fs::File,
+    io::{self, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader},
+    process::{ChildStderr, ChildStdout},
+    sync::mpsc::{self, error::TrySendError, Receiver, Sender},
+    task::JoinHandle,
+    time::{self, Instant},
+};
+
+// Processes that outlive the game, like wineserver, can keep the pipes open. Whatever they
+// write after this is lost, so that gtnkr doesn't wait for them.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Lines that are read but not written yet, the game blocks on its output once it's full.
+const LINE_BUFFER: usize = 1024;
+
+// Longer lines are split, so that output without newlines doesn't have to fit in memory.
+const MAX_LINE_LENGTH: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    pub fn as_tag(&self) -> &str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+
+    fn as_ansi_color(&self) -> &str {
+        match self {
+            Self::Stdout => "\x1b[36m",
+            Self::Stderr => "\x1b[31m",
+        }
+    }
+}
+
+// Streams the game's output to gtnkr's own stdout and stderr while it's being logged.
+#[derive(Debug)]
+pub struct OutputTee {
+    // Only lines that match are shown, the logs always contain every line.
+    filter: Option<Regex>,
+    colored: bool,
+}
+
+impl OutputTee {
+    // Colors are used if gtnkr's output is a terminal and $NO_COLOR isn't set.
+    pub fn new(filter: Option<Regex>) -> Self {
+        let colored = env::var_os("NO_COLOR").unwrap_or_default().is_empty()
+            && std::io::stdout().is_terminal()
+            && std::io::stderr().is_terminal();
+
+        Self { filter, colored }
+    }
+
+    fn format_line(&self, stream: OutputStream, line: &[u8]) -> Option<Vec<u8>> {
+        if let Some(filter) = &self.filter {
+            if !filter.is_match(&String::from_utf8_lossy(line)) {
+                return None;
+            }
+        }
+
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let mut formatted_line = Vec::with_capacity(line.len() + 10);
+
+        if self.colored {
+            formatted_line.extend_from_slice(stream.as_ansi_color().as_bytes());
+            formatted_line.extend_from_slice(line);
+            formatted_line.extend_from_slice(b"\x1b[0m\n");
+        } else {
+            formatted_line.extend_from_slice(line);
+            formatted_line.push(b'\n');
+        }
+
+        Some(formatted_line)
+    }
+}
+
+// A single task writes both streams, so that their lines show up in order. It stops after the
+// first error, e.g. when the terminal was closed, the logs aren't affected by it.
+async fn write_tee_lines(mut receiver: Receiver<TeeLine>) {
+    let mut stdout = io::stdout();
+    let mut stderr = io::stderr();
+
+    while let Some((stream, line)) = receiver.recv().await {
+        let result = match stream {
+            OutputStream::Stdout => stdout.write_all(&line).await,
+            OutputStream::Stderr => stderr.write_all(&line).await,
+        };
+
+        if let Err(error) = result {
+            tracing::warn!("Failed to print the game's {} with --tee, it's only written to the logs from now on. {error}", stream.as_tag());
+
+            return;
+        }
+    }
+
+    let _ = stdout.flush().await;
+    let _ = stderr.flush().await;
+}
+
+type TeeLine = (OutputStream, Vec<u8>);
+
+// Lines are left out of the console instead of waiting for it, e.g. while the terminal is
+// paused, so that it can't stall the logs. How many were left out is shown once there's room.
+struct TeeSender {
+    tee: OutputTee,
+    sender: Sender<TeeLine>,
+    dropped_lines: usize,
+}
+
+impl TeeSender {
+    fn new(tee: OutputTee, sender: Sender<TeeLine>) -> Self {
+        Self {
+            tee,
+            sender,
+            dropped_lines: 0,
+        }
+    }
+
+    // Returns false once the console writer stopped.
+    fn send(&mut self, stream: OutputStream, line: &[u8]) -> bool {
+        let Some(formatted_line) = self.tee.format_line(stream, line) else {
+            return true;
+        };
+
+        if self.dropped_lines > 0 {
+            match self.sender.try_send(self.dropped_lines_marker()) {
+                Ok(()) => self.dropped_lines = 0,
+                Err(TrySendError::Full(_)) => {
+                    self.dropped_lines += 1;
+
+                    return true;
+                }
+                Err(TrySendError::Closed(_)) => return false,
+            }
+        }
+
+        match self.sender.try_send((stream, formatted_line)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped_lines += 1;
+
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    // Lines dropped at the end are still reported if there's room, it isn't waited for.
+    fn finish(self) {
+        if self.dropped_lines > 0 {
+            let _ = self.sender.try_send(self.dropped_lines_marker());
+        }
+    }
+
+    fn dropped_lines_marker(&self) -> TeeLine {
+        (
+            OutputStream::Stderr,
+            format!("[gtnkr: {} lines dropped]\n", self.dropped_lines).into_bytes(),
+        )
+    }
+}
+
+struct OutputLine {
+    stream: OutputStream,
+    // Time since the capture started, a monotonic clock so that lines are ordered correctly.
+    elapsed: Duration,
+    line: Vec<u8>,
+}
+
+pub struct OutputLogFiles {
+    pub stdout: File,
+    pub stderr: File,
+    // Every line of both streams in the order they were read, prefixed with the time and stream.
+    pub combined: Option<File>,
+}
+
+// Stops writing after the first error, e.g. when the disk is full, the output is still drained
+// so that the game doesn't get SIGPIPE. The error is reported once the capture finishes.
+struct OutputLogFile {
+    file: File,
+    name: &'static str,
+    error: Option<io::Error>,
+}
+
+impl OutputLogFile {
+    fn new(file: File, name: &'static str) -> Self {
+        Self {
+            file,
+            name,
+            error: None,
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Err(error) = self.file.write_all(bytes).await {
+            tracing::warn!(
+                "Failed to write the {} log, the rest of the game's output won't be written to it. {error}",
+                self.name
+            );
+
+            self.error = Some(error);
+        }
+    }
+
+    async fn finish(mut self) -> io::Result<()> {
+        match self.error {
+            Some(error) => Err(error),
+            None => self.file.flush().await,
+        }
+    }
+}
+
+pub struct OutputCapture {
+    readers: Vec<JoinHandle<io::Result<()>>>,
+    writer: JoinHandle<io::Result<()>>,
+    tee_writer: Option<JoinHandle<()>>,
+}
+
+async fn read_lines<R>(
+    reader: R,
+    stream: OutputStream,
+    start: Instant,
+    sender: Sender<OutputLine>,
+) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let mut line = Vec::new();
+
+        if (&mut reader)
+            .take(MAX_LINE_LENGTH)
+            .read_until(b'\n', &mut line)
+            .await?
+            == 0
+        {
+            return Ok(());
+        }
+
+        let output_line = OutputLine {
+            stream,
+            elapsed: start.elapsed(),
+            line,
+        };
+
+        if sender.send(output_line).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+fn format_combined_line(output_line: &OutputLine) -> Vec<u8> {
+    let mut combined_line = format!(
+        "[{:>6}.{:06}] {}: ",
+        output_line.elapsed.as_secs(),
+        output_line.elapsed.subsec_micros(),
+        output_line.stream.as_tag()
+    )
+    .into_bytes();
+
+    combined_line.extend_from_slice(&output_line.line);
+
+    if !combined_line.ends_with(b"\n") {
+        combined_line.push(b'\n');
+    }
+
+    combined_line
+}
+
+impl OutputCapture {
+    pub fn start(
+        stdout: ChildStdout,
+        stderr: ChildStderr,
+        files: OutputLogFiles,
+        tee: Option<OutputTee>,
+    ) -> Self {
+        let start = Instant::now();
+        let (sender, mut receiver) = mpsc::channel::<OutputLine>(LINE_BUFFER);
+
+        let readers = vec![
+            tokio::spawn(read_lines(
+                stdout,
+                OutputStream::Stdout,
+                start,
+                sender.clone(),
+            )),
+            tokio::spawn(read_lines(stderr, OutputStream::Stderr, start, sender)),
+        ];
+
+        let mut stdout_log = OutputLogFile::new(files.stdout, "stdout");
+        let mut stderr_log = OutputLogFile::new(files.stderr, "stderr");
+        let mut combined_log = files
+            .combined
+            .map(|combined| OutputLogFile::new(combined, "combined"));
+
+        let (mut tee_sender, tee_writer) = match tee {
+            Some(tee) => {
+                let (tee_sender, tee_receiver) = mpsc::channel(LINE_BUFFER);
+
+                (
+                    Some(TeeSender::new(tee, tee_sender)),
+                    Some(tokio::spawn(write_tee_lines(tee_receiver))),
+                )
+            }
+            None => (None, None),
+        };
+
+        let writer = tokio::spawn(async move {
+            while let Some(output_line) = receiver.recv().await {
+                let stream_log = match output_line.stream {
+                    OutputStream::Stdout => &mut stdout_log,
+                    OutputStream::Stderr => &mut stderr_log,
+                };
+
+                stream_log.write(&output_line.line).await;
+
+                if let Some(combined_log) = &mut combined_log {
+                    combined_log
+                        .write(&format_combined_line(&output_line))
+                        .await;
+                }
+
+                if let Some(sender) = &mut tee_sender {
+                    if !sender.send(output_line.stream, &output_line.line) {
+                        tee_sender = None;
+                    }
+                }
+            }
+
+            if let Some(sender) = tee_sender {
+                sender.finish();
+            }
+
+            // Every log is finished, the first error is reported.
+            let results = [
+                stdout_log.finish().await,
+                stderr_log.finish().await,
+                match combined_log {
+                    Some(combined_log) => combined_log.finish().await,
+                    None => Ok(()),
+                },
+            ];
+
+            results.into_iter().collect()
+        });
+
+        Self {
+            readers,
+            writer,
+            tee_writer,
+        }
+    }
+
+    // Waits until both streams are closed and every line is written, should be called after
+    // the process exited.
+    pub async fn finish(self) -> io::Result<()> {
+        let deadline = Instant::now() + DRAIN_TIMEOUT;
+        let mut timed_out = false;
+        let mut results = Vec::new();
+
+        // Every task is awaited or aborted even after an error, the first error is reported.
+        for reader in self.readers {
+            let abort_handle = reader.abort_handle();
+
+            match time::timeout_at(deadline, reader).await {
+                Ok(result) => results.push(result.unwrap_or_else(|error| Err(error.into()))),
+                Err(_) => {
+                    timed_out = true;
+                    abort_handle.abort();
+                }
+            }
+        }
+
+        if timed_out {
+            tracing::debug!("The game's output is still open after it exited, it's probably held by a process that outlived it. Ignoring the rest of it.");
+        }
+
+        results.push(self.writer.await.unwrap_or_else(|error| Err(error.into())));
+
+        if let Some(tee_writer) = self.tee_writer {
+            let abort_handle = tee_writer.abort_handle();
+
+            if time::timeout(DRAIN_TIMEOUT, tee_writer).await.is_err() {
+                abort_handle.abort();
+            }
+        }
+
+        results.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutputCapture, OutputLogFiles, OutputStream, OutputTee, TeeSender};
+    use color_eyre::eyre;
+    use regex::Regex;
+    use std::process::Stdio;
+    use tempdir::TempDir;
+    use tokio::{fs, process::Command, sync::mpsc};
+
+    #[tokio::test]
+    async fn capture_both_streams_and_interleave_them() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("capture_both_streams_and_interleave_them")?;
+        let path = |name: &str| temp_dir.path().join(name);
+
+        let mut process = Command::new("sh")
+            .args([
+                "-c",
+                "echo first; sleep 0.1; echo second >&2; sleep 0.1; printf 'no newline'",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let capture = OutputCapture::start(
+            process.stdout.take().expect("stdout should be piped"),
+            process.stderr.take().expect("stderr should be piped"),
+            OutputLogFiles {
+                stdout: fs::File::create(path("outlog")).await?,
+                stderr: fs::File::create(path("errlog")).await?,
+                combined: Some(fs::File::create(path("log")).await?),
+            },
+            // Doesn't print anything, but goes through the console writer.
+            Some(OutputTee {
+                filter: Some(Regex::new("^not printed$")?),
+                colored: false,
+            }),
+        );
+
+        process.wait().await?;
+        capture.finish().await?;
+
+        assert_eq!(
+            fs::read_to_string(path("outlog")).await?,
+            "first\nno newline"
+        );
+        assert_eq!(fs::read_to_string(path("errlog")).await?, "second\n");
+
+        let combined_line = Regex::new(r"^\[ +\d+\.\d{6}\] (stdout|stderr): (.*)$")?;
+        let combined = fs::read_to_string(path("log")).await?;
+        let lines: Vec<(String, String)> = combined
+            .lines()
+            .filter_map(|line| {
+                let captures = combined_line.captures(line)?;
+
+                Some((captures[1].to_string(), captures[2].to_string()))
+            })
+            .collect();
+
+        assert_eq!(
+            lines,
+            [
+                (String::from("stdout"), String::from("first")),
+                (String::from("stderr"), String::from("second")),
+                (String::from("stdout"), String::from("no newline")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keep_draining_after_a_write_error() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("keep_draining_after_a_write_error")?;
+        let path = |name: &str| temp_dir.path().join(name);
+
+        fs::write(path("outlog"), "").await?;
+
+        // More than fits into the pipe, and a line longer than the line length cap.
+        let mut process = Command::new("sh")
+            .args([
+                "-c",
+                "seq 1 100000; head -c 200000 /dev/zero | tr '\\0' a >&2",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let capture = OutputCapture::start(
+            process.stdout.take().expect("stdout should be piped"),
+            process.stderr.take().expect("stderr should be piped"),
+            OutputLogFiles {
+                // Opened read-only, so every write fails.
+                stdout: fs::File::open(path("outlog")).await?,
+                stderr: fs::File::create(path("errlog")).await?,
+                combined: None,
+            },
+            None,
+        );
+
+        assert!(process.wait().await?.success());
+        assert!(capture.finish().await.is_err());
+        assert_eq!(
+            fs::read_to_string(path("errlog")).await?,
+            "a".repeat(200000)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter_and_color_tee_lines() -> eyre::Result<()> {
+        let tee = OutputTee {
+            filter: Some(Regex::new(r"^[0-9a-f]+:(err|fixme):")?),
+            colored: true,
+        };
+
+        assert_eq!(
+            tee.format_line(
+                OutputStream::Stderr,
+                b"0024:err:module:import_dll Library MSVCP140.dll not found\n"
+            ),
+            Some(
+                b"\x1b[31m0024:err:module:import_dll Library MSVCP140.dll not found\x1b[0m\n"
+                    .to_vec()
+            )
+        );
+        assert_eq!(
+            tee.format_line(OutputStream::Stderr, b"0024:trace:seh:dispatch_exception\n"),
+            None
+        );
+
+        let tee = OutputTee {
+            filter: None,
+            colored: false,
+        };
+
+        assert_eq!(
+            tee.format_line(OutputStream::Stdout, b"no newline"),
+            Some(b"no newline\n".to_vec())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn count_tee_lines_dropped_while_the_console_is_full() {
+        let (sender, mut receiver) = mpsc::channel(2);
+        let mut tee_sender = TeeSender::new(
+            OutputTee {
+                filter: None,
+                colored: false,
+            },
+            sender,
+        );
+
+        for line in ["1", "2", "3", "4"] {
+            assert!(tee_sender.send(OutputStream::Stdout, line.as_bytes()));
+        }
+
+        let mut receive = || receiver.try_recv().ok().map(|(_, line)| line);
+
+        assert_eq!(receive(), Some(b"1\n".to_vec()));
+        assert_eq!(receive(), Some(b"2\n".to_vec()));
+        assert_eq!(receive(), None);
+
+        assert!(tee_sender.send(OutputStream::Stdout, b"5"));
+        assert_eq!(receive(), Some(b"[gtnkr: 2 lines dropped]\n".to_vec()));
+        assert_eq!(receive(), Some(b"5\n".to_vec()));
+
+        // Only the marker fits, the line after it is counted again.
+        for line in ["6", "7", "8"] {
+            assert!(tee_sender.send(OutputStream::Stderr, line.as_bytes()));
+        }
+
+        assert_eq!(receive(), Some(b"6\n".to_vec()));
+        assert!(tee_sender.send(OutputStream::Stderr, b"9"));
+        assert_eq!(receive(), Some(b"7\n".to_vec()));
+        assert_eq!(receive(), Some(b"[gtnkr: 1 lines dropped]\n".to_vec()));
+
+        tee_sender.finish();
+
+        assert_eq!(receive(), Some(b"[gtnkr: 1 lines dropped]\n".to_vec()));
+    }
+}
@@ -0,0 +1,276 @@
+use super::LaunchPlan;
+use crate::config::{discover_icd_manifests, icd_directories, installed_gpus};
+use crate::process_output_log::{
+    write_session_metadata, GpuInfo, ProcessOutputLogError, SessionMetadata, SystemInfo,
+    VulkanDriverInfo,
+};
+use chrono::{DateTime, Local, NaiveDateTime};
+use hyprland::{data::Version as HyprlandVersion, shared::HyprData};
+use std::{env, os::unix::process::ExitStatusExt, path::Path, process::ExitStatus, time::Duration};
+use tokio::{fs, process::Command, time};
+
+const KERNEL_RELEASE_PATH: &str = "/proc/sys/kernel/osrelease";
+
+// A compositor that doesn't answer doesn't keep gtnkr from exiting after the game.
+const DETECTION_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn get_env(variable: &str) -> Option<String> {
+    env::var(variable).ok().filter(|value| !value.is_empty())
+}
+
+async fn detect_kernel() -> Option<String> {
+    fs::read_to_string(KERNEL_RELEASE_PATH)
+        .await
+        .ok()
+        .map(|release| release.trim().to_string())
+}
+
+// E.g. `sway version 1.9` from `sway --version`.
+async fn program_version(program: &str) -> Option<String> {
+    let output = Command::new(program)
+        .arg("--version")
+        .kill_on_drop(true)
+        .output();
+
+    let output = time::timeout(DETECTION_TIMEOUT, output).await.ok()?.ok()?;
+
+    String::from_utf8(output.stdout)
+        .ok()?
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+}
+
+// The name of the compositor and its version, if it can be asked for one.
+async fn detect_compositor() -> (Option<String>, Option<String>) {
+    if get_env("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        let version = time::timeout(DETECTION_TIMEOUT, HyprlandVersion::get_async())
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .map(|version| {
+                let commit: String = version.commit.chars().take(7).collect();
+
+                format!("{} ({commit})", version.branch)
+            });
+
+        return (Some(String::from("Hyprland")), version);
+    }
+
+    if get_env("SWAYSOCK").is_some() {
+        return (Some(String::from("sway")), program_version("sway").await);
+    }
+
+    let desktop = get_env("XDG_CURRENT_DESKTOP");
+    let session_type = get_env("XDG_SESSION_TYPE");
+
+    let compositor = match (desktop, session_type) {
+        (Some(desktop), Some(session_type)) => Some(format!("{desktop} ({session_type})")),
+        (desktop, session_type) => desktop.or(session_type),
+    };
+
+    (compositor, None)
+}
+
+fn detect_gpus(plan: &LaunchPlan) -> Vec<GpuInfo> {
+    let gpus = match installed_gpus() {
+        Ok(gpus) => gpus,
+        Err(error) => {
+            tracing::debug!("Couldn't list the GPUs for the session metadata. {error}");
+
+            return Vec::new();
+        }
+    };
+
+    gpus.into_iter()
+        .map(|gpu| GpuInfo {
+            selected: plan
+                .gpu
+                .as_ref()
+                .is_some_and(|selected_gpu| selected_gpu.pci_address == gpu.pci_address),
+            pci_id: gpu.pci_id(),
+            pci_address: gpu.pci_address,
+            driver: gpu.driver,
+            boot_vga: gpu.boot_vga,
+        })
+        .collect()
+}
+
+fn detect_vulkan_drivers() -> Vec<VulkanDriverInfo> {
+    let manifests = match discover_icd_manifests(&icd_directories()) {
+        Ok(manifests) => manifests,
+        Err(error) => {
+            tracing::debug!("Couldn't list the Vulkan drivers for the session metadata. {error}");
+
+            return Vec::new();
+        }
+    };
+
+    manifests
+        .into_iter()
+        .map(|manifest| VulkanDriverInfo {
+            driver: manifest.driver.map(|driver| format!("{driver:?}")),
+            api_version: manifest.api_version,
+            manifest_path: manifest.path,
+            library_path: manifest.library_path,
+        })
+        .collect()
+}
+
+// Detection is best effort, whatever can't be detected is left out.
+pub async fn collect_session_metadata(
+    plan: &LaunchPlan,
+    started_at: DateTime<Local>,
+    ended_at: DateTime<Local>,
+    duration: Duration,
+    exit_status: ExitStatus,
+) -> SessionMetadata {
+    let (compositor, compositor_version) = detect_compositor().await;
+
+    let config = serde_json::to_value(&plan.config).unwrap_or_else(|error| {
+        tracing::debug!("Couldn't serialize the game config for the session metadata. {error}");
+
+        serde_json::Value::Null
+    });
+
+    SessionMetadata {
+        gtnkr_version: env!("CARGO_PKG_VERSION").to_string(),
+        app_id: plan.game_identifier.clone(),
+        config_file: plan.config_file.clone(),
+        config,
+        command: plan.command_chain.as_argv(),
+        environment: plan
+            .environment
+            .resolve()
+            .into_iter()
+            .map(|(key, value)| (key, value.map(|value| value.to_string_lossy().into_owned())))
+            .collect(),
+        started_at: Some(started_at),
+        ended_at: Some(ended_at),
+        duration_secs: duration.as_secs_f64(),
+        exit_code: exit_status.code(),
+        signal: exit_status.signal(),
+        system: SystemInfo {
+            kernel: detect_kernel().await,
+            compositor,
+            compositor_version,
+            gamescope_version: plan.gamescope_version.clone(),
+            gpus: detect_gpus(plan),
+            vulkan_drivers: detect_vulkan_drivers(),
+        },
+    }
+}
+
+// Next to the active logs on every launch, and next to the persistent logs if they're kept.
+// Both are written even if one fails, the first error is reported.
+pub(super) fn write_session_metadata_files(
+    metadata: &SessionMetadata,
+    session_timestamp: &NaiveDateTime,
+    active_log_directory: &Path,
+    persistent_log_directory: Option<&Path>,
+) -> Result<(), ProcessOutputLogError> {
+    let active = write_session_metadata(active_log_directory, session_timestamp, metadata);
+    let persistent = match persistent_log_directory {
+        Some(persistent_log_directory) => {
+            write_session_metadata(persistent_log_directory, session_timestamp, metadata)
+        }
+        None => Ok(()),
+    };
+
+    active.and(persistent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collect_session_metadata, write_session_metadata_files};
+    use crate::game_launcher::launch_plan::tests::launch_plan;
+    use crate::process_output_log::{find_sessions, SessionMetadata};
+    use chrono::{Local, TimeDelta};
+    use color_eyre::eyre;
+    use std::{collections::BTreeMap, os::unix::process::ExitStatusExt, process::ExitStatus};
+    use tempdir::TempDir;
+
+    #[tokio::test]
+    async fn collect_the_metadata_of_a_launch_plan() -> eyre::Result<()> {
+        let plan = launch_plan()?;
+        let ended_at = Local::now();
+        let started_at = ended_at - TimeDelta::seconds(90);
+
+        let metadata = collect_session_metadata(
+            &plan,
+            started_at,
+            ended_at,
+            std::time::Duration::from_secs(90),
+            ExitStatus::from_raw(3 << 8),
+        )
+        .await;
+
+        assert_eq!(metadata.app_id, "1145360");
+        assert_eq!(metadata.config_file, plan.config_file);
+        assert_eq!(metadata.command, plan.command_chain.as_argv());
+        assert_eq!(
+            metadata.environment,
+            BTreeMap::from([
+                (
+                    String::from("GTNKR_LAUNCH_PLAN_TEST"),
+                    Some(String::from("a b"))
+                ),
+                (String::from("GTNKR_LAUNCH_PLAN_TEST_REMOVED"), None),
+            ])
+        );
+        assert_eq!(metadata.config["fps_limit"], serde_json::json!(0));
+        assert_eq!(metadata.started_at, Some(started_at));
+        assert_eq!(metadata.ended_at, Some(ended_at));
+        assert_eq!(metadata.duration_secs, 90.0);
+        assert_eq!((metadata.exit_code, metadata.signal), (Some(3), None));
+        assert_eq!(metadata.system.gamescope_version, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_the_metadata_without_persistent_logs() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("write_the_metadata_without_persistent_logs")?;
+        let active_log_directory = temp_dir.path().join("active");
+        let persistent_log_directory = temp_dir.path().join("persistent");
+        let session_timestamp = Local::now().naive_local();
+        let metadata = SessionMetadata {
+            app_id: String::from("1145360"),
+            exit_code: Some(0),
+            ..SessionMetadata::default()
+        };
+
+        std::fs::create_dir_all(&active_log_directory)?;
+        std::fs::create_dir_all(&persistent_log_directory)?;
+
+        // Like a launch without `--log-output`.
+        write_session_metadata_files(&metadata, &session_timestamp, &active_log_directory, None)?;
+
+        let written_metadata = |directory| -> eyre::Result<Vec<Option<SessionMetadata>>> {
+            find_sessions(directory)?
+                .iter()
+                .map(|session| Ok(session.metadata()?))
+                .collect()
+        };
+
+        assert_eq!(
+            written_metadata(&active_log_directory)?,
+            [Some(metadata.clone())]
+        );
+        assert_eq!(written_metadata(&persistent_log_directory)?, []);
+
+        write_session_metadata_files(
+            &metadata,
+            &session_timestamp,
+            &active_log_directory,
+            Some(&persistent_log_directory),
+        )?;
+
+        assert_eq!(
+            written_metadata(&persistent_log_directory)?,
+            [Some(metadata)]
+        );
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 pub mod cli;
 mod config;
-mod game_launcher;
+pub mod game_launcher;
 pub mod process_output_log;
+pub mod resolution_provider;
+pub mod steam_launch_command;
 
 lazy_static::lazy_static! {
     pub static ref UPPERCASE_PACKAGE_NAME: String = {
@@ -11,4 +13,10 @@ lazy_static::lazy_static! {
     pub static ref LOWERCASE_PACKAGE_NAME: String = {
         env!("CARGO_PKG_NAME").to_lowercase()
     };
+
+    pub static ref RUNTIME_DIRECTORY: std::path::PathBuf = {
+        let uid = nix::unistd::getuid();
+
+        std::path::PathBuf::from(format!("/run/user/{uid}/{}", LOWERCASE_PACKAGE_NAME.as_str()))
+    };
 }
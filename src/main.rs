@@ -1,4 +1,4 @@
-use gtnkr::{process_output_log::RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY, UPPERCASE_PACKAGE_NAME};
+use gtnkr::UPPERCASE_PACKAGE_NAME;
 use std::env;
 use tracing::{subscriber, Level};
 use tracing_subscriber::FmtSubscriber;
@@ -9,17 +9,15 @@ async fn main() -> color_eyre::Result<()> {
 
     setup_debugging();
 
-    gtnkr::cli::run().await?;
+    let exit_status = gtnkr::cli::run().await?;
 
-    cleanup_runtime_dir();
+    if let Some(exit_status) = exit_status {
+        gtnkr::game_launcher::exit_like(exit_status);
+    }
 
     Ok(())
 }
 
-fn cleanup_runtime_dir() {
-    let _ = std::fs::remove_dir_all(RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY.as_path());
-}
-
 fn setup_debugging() {
     let mut tracing_max_level = Level::INFO;
     let debug_env_var_key = format!("{}_DEBUG", UPPERCASE_PACKAGE_NAME.as_str());
@@ -0,0 +1,96 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use similar::TextDiff;
+
+lazy_static! {
+    // In the order they're applied, so that the prefix of the combined log and full timestamps
+    // aren't mangled by the more general patterns.
+    static ref VOLATILE_PATTERNS: [(Regex, &'static str); 5] = [
+        (
+            Regex::new(r"(?m)^\[ *\d+\.\d{6}\]").unwrap(),
+            "[<elapsed>]"
+        ),
+        (
+            Regex::new(r"\d{4}-\d{2}-\d{2}[T _]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?").unwrap(),
+            "<timestamp>"
+        ),
+        (
+            Regex::new(r"\b\d{2}:\d{2}:\d{2}(?:[.,]\d+)?\b").unwrap(),
+            "<time>"
+        ),
+        (Regex::new(r"\b0x[0-9a-fA-F]+\b").unwrap(), "0x<address>"),
+        // Wine's thread IDs, e.g. `0024:err:module:import_dll`.
+        (
+            Regex::new(r"(?m)(^|: )[0-9a-f]{4}:(err|warn|fixme|trace):").unwrap(),
+            "${1}<thread>:${2}:"
+        ),
+    ];
+}
+
+// Replaces what changes between otherwise identical runs, like times and addresses, so that
+// only the differences that matter are left.
+pub fn normalize_log(log: &str) -> String {
+    VOLATILE_PATTERNS
+        .iter()
+        .fold(log.to_string(), |log, (pattern, replacement)| {
+            pattern.replace_all(&log, *replacement).into_owned()
+        })
+}
+
+// A unified diff of the normalized logs, empty if they're the same.
+pub fn diff_logs(old_log: &str, new_log: &str, old_name: &str, new_name: &str) -> String {
+    let old_log = normalize_log(old_log);
+    let new_log = normalize_log(new_log);
+
+    if old_log == new_log {
+        return String::new();
+    }
+
+    TextDiff::from_lines(&old_log, &new_log)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_name, new_name)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_logs, normalize_log};
+
+    #[test]
+    fn normalize_and_diff_logs() {
+        let old_log = "\
+[     0.001234] stderr: 0024:err:module:import_dll Library MSVCP140.dll not found
+[     1.500000] stdout: Started at 2024-06-01 10:00:00.123 (10:00:00)
+[     2.000000] stderr: wine: Unhandled page fault on read access to 0x7FFE0000 at 0x00401000
+";
+        let new_log = "\
+[     0.003456] stderr: 0104:err:module:import_dll Library MSVCP140.dll not found
+[     1.700000] stdout: Started at 2024-06-02T11:30:00 (11:30:00)
+[     2.900000] stderr: wine: Unhandled page fault on write access to 0x12345678 at 0x00402000
+";
+
+        assert_eq!(
+            normalize_log(old_log).lines().take(2).collect::<Vec<_>>(),
+            [
+                "[<elapsed>] stderr: <thread>:err:module:import_dll Library MSVCP140.dll not found",
+                "[<elapsed>] stdout: Started at <timestamp> (<time>)",
+            ]
+        );
+
+        assert_eq!(
+            diff_logs(old_log, new_log, "old", "new"),
+            "\
+--- old
++++ new
+@@ -1,3 +1,3 @@
+ [<elapsed>] stderr: <thread>:err:module:import_dll Library MSVCP140.dll not found
+ [<elapsed>] stdout: Started at <timestamp> (<time>)
+-[<elapsed>] stderr: wine: Unhandled page fault on read access to 0x<address> at 0x<address>
++[<elapsed>] stderr: wine: Unhandled page fault on write access to 0x<address> at 0x<address>
+"
+        );
+
+        assert_eq!(diff_logs(old_log, old_log, "old", "new"), "");
+    }
+}
@@ -1,6 +1,6 @@
-use crate::LOWERCASE_PACKAGE_NAME;
+use crate::{LOWERCASE_PACKAGE_NAME, RUNTIME_DIRECTORY};
 use chrono::{Local, NaiveDateTime};
-use nix::unistd::{getuid, User};
+use nix::unistd::{getuid, Uid, User};
 use std::{
     fs,
     fs::{copy, File},
@@ -9,30 +9,43 @@ use std::{
     process::Stdio,
 };
 
+mod diff;
+mod retention;
+mod session;
+
+pub use diff::{diff_logs, normalize_log};
+pub use retention::{prune_sessions, PruneReport};
+pub use session::{
+    find_logged_identifiers, find_sessions, open_log, read_log_to_string, write_session_metadata,
+    GpuInfo, LogSession, SessionExit, SessionMetadata, SystemInfo, VulkanDriverInfo,
+};
+
 pub const READABLE_TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H:%M:%S";
 
 lazy_static::lazy_static! {
     pub static ref RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY: PathBuf = {
-        let uid = getuid();
-        let package_name = LOWERCASE_PACKAGE_NAME.as_str();
-
-        PathBuf::from(format!("/run/user/{uid}/{package_name}/process-output-logs"))
+        RUNTIME_DIRECTORY.join("process-output-logs")
     };
+}
 
-    pub static ref PERSISTENT_PROCESS_OUTPUT_LOG_DIRECTORY: PathBuf = {
-        let uid = getuid();
-        let user = User::from_uid(uid).expect("Should've been able get the current linux user by uid").expect("Linux user should've existed");
+pub fn persistent_process_output_log_directory() -> Result<PathBuf, ProcessOutputLogError> {
+    let uid = getuid();
+    let user = User::from_uid(uid)
+        .map_err(ProcessOutputLogError::LookupCurrentUser)?
+        .ok_or(ProcessOutputLogError::MissingCurrentUser(uid))?;
 
-        let home_directory = user.dir;
-        let package_name = LOWERCASE_PACKAGE_NAME.as_str();
+    let home_directory = user.dir;
+    let package_name = LOWERCASE_PACKAGE_NAME.as_str();
 
-        home_directory.join(format!("{package_name}/process-output-logs"))
-    };
+    Ok(home_directory.join(format!("{package_name}/process-output-logs")))
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ProcessOutputLogKind {
     Stderr,
     Stdout,
+    // Both streams interleaved, every line prefixed with the time since launch and its stream.
+    Combined,
 }
 
 impl ProcessOutputLogKind {
@@ -40,6 +53,7 @@ impl ProcessOutputLogKind {
         match self {
             Self::Stderr => "errlog",
             Self::Stdout => "outlog",
+            Self::Combined => "log",
         }
     }
 }
@@ -57,8 +71,32 @@ pub enum ProcessOutputLogError {
     )]
     CreateStdioFromOutputLog(IoError, PathBuf),
 
+    #[error("Failed to look up the current user to find the home directory, see: {0}")]
+    LookupCurrentUser(nix::Error),
+
+    #[error("The current user (uid {0}) doesn't exist in the user database, so there's no home directory to persist the output log in")]
+    MissingCurrentUser(Uid),
+
     #[error("IO error while attempting to copy the contents of the runtime output log `{1:#?}` to the persistent output log `{2:#?}`, see: {0:#?}")]
     CopyRuntimeToPersistent(IoError, PathBuf, PathBuf),
+
+    #[error("IO error while attempting to read the output log directory `{1:#?}`, see: {0:#?}")]
+    ReadLogDirectory(IoError, PathBuf),
+
+    #[error("IO error while attempting to remove the output log `{1:#?}`, see: {0:#?}")]
+    RemoveLog(IoError, PathBuf),
+
+    #[error("IO error while attempting to compress the output log `{1:#?}`, see: {0:#?}")]
+    CompressLog(IoError, PathBuf),
+
+    #[error("IO error while attempting to read the output log `{1:#?}`, see: {0:#?}")]
+    ReadLog(IoError, PathBuf),
+
+    #[error("Invalid session exit status `{0}` in `{1:#?}`")]
+    ParseSessionExit(String, PathBuf),
+
+    #[error("Failed to parse the session metadata `{1:#?}`, see: {0}")]
+    ParseSessionMetadata(serde_json::Error, PathBuf),
 }
 
 type ProcessOutputLogResult<T> = Result<T, ProcessOutputLogError>;
@@ -135,16 +173,30 @@ pub fn create(
     kind: ProcessOutputLogKind,
     base_log_directory_path: PathBuf,
 ) -> (String, NaiveDateTime, ProcessOutputLogKind, PathBuf) {
-    let current_timestamp: NaiveDateTime = Local::now().naive_local();
+    create_at(
+        identifier,
+        kind,
+        base_log_directory_path,
+        Local::now().naive_local(),
+    )
+}
 
+// The logs of a single launch are created with the same timestamp, so that they're grouped
+// into one session.
+pub fn create_at(
+    identifier: String,
+    kind: ProcessOutputLogKind,
+    base_log_directory_path: PathBuf,
+    timestamp: NaiveDateTime,
+) -> (String, NaiveDateTime, ProcessOutputLogKind, PathBuf) {
     let _ = create_output_log_file(
         &identifier,
-        &current_timestamp,
+        &timestamp,
         &kind,
         base_log_directory_path.clone(),
     );
 
-    (identifier, current_timestamp, kind, base_log_directory_path)
+    (identifier, timestamp, kind, base_log_directory_path)
 }
 
 pub struct ActiveOutputLog {
@@ -191,6 +243,35 @@ impl ProcessOutputLog for ActiveOutputLog {
     }
 }
 
+impl ActiveOutputLog {
+    pub fn create_at<S: ToString>(
+        identifier: S,
+        kind: ProcessOutputLogKind,
+        timestamp: NaiveDateTime,
+    ) -> Self {
+        let (identifier, timestamp, kind, base_log_directory_path) = create_at(
+            identifier.to_string(),
+            kind,
+            RUNTIME_PROCESS_OUTPUT_LOG_DIRECTORY.to_path_buf(),
+            timestamp,
+        );
+
+        Self {
+            identifier,
+            timestamp,
+            kind,
+            base_log_directory_path,
+        }
+    }
+
+    // Only the files of this launch are removed, another game may still be writing its logs.
+    pub fn remove(self) -> ProcessOutputLogResult<()> {
+        let path = self.as_path();
+
+        fs::remove_file(&path).map_err(|error| ProcessOutputLogError::RemoveLog(error, path))
+    }
+}
+
 pub struct PersistentOutputLog {
     identifier: String,
     timestamp: NaiveDateTime,
@@ -206,7 +287,7 @@ impl ProcessOutputLog for PersistentOutputLog {
         let (identifier, timestamp, kind, base_log_directory_path) = create(
             identifier.to_string(),
             kind,
-            PERSISTENT_PROCESS_OUTPUT_LOG_DIRECTORY.to_path_buf(),
+            persistent_process_output_log_directory()?,
         );
 
         Ok(Self {
@@ -236,9 +317,20 @@ impl ProcessOutputLog for PersistentOutputLog {
 }
 
 impl PersistentOutputLog {
-    pub fn from_active_output_log(active_log: ActiveOutputLog) -> ProcessOutputLogResult<Self> {
+    pub fn from_active_output_log(active_log: &ActiveOutputLog) -> ProcessOutputLogResult<Self> {
         let active_file_path = active_log.as_path();
-        let persistent_log = PersistentOutputLog::create(active_log.identifier, active_log.kind)?;
+        let (identifier, timestamp, kind, base_log_directory_path) = create_at(
+            active_log.identifier.clone(),
+            active_log.kind,
+            persistent_process_output_log_directory()?,
+            active_log.timestamp,
+        );
+        let persistent_log = Self {
+            identifier,
+            timestamp,
+            kind,
+            base_log_directory_path,
+        };
         let persistent_file_path = persistent_log.as_path();
 
         copy(&active_file_path, &persistent_file_path).map_err(|error| {
@@ -0,0 +1,289 @@
+use super::{find_sessions, session::is_uncompressed_log, LogSession, ProcessOutputLogError};
+use crate::config::{LogCompression, LogRetention};
+use chrono::{Duration, NaiveDateTime};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PruneReport {
+    pub removed_sessions: usize,
+    pub compressed_files: usize,
+    pub freed_bytes: u64,
+}
+
+fn compress_file(path: &Path, compression: LogCompression) -> io::Result<Option<PathBuf>> {
+    let Some(compressed_extension) = compression.as_file_extension() else {
+        return Ok(None);
+    };
+
+    let mut compressed_path = path.as_os_str().to_owned();
+    compressed_path.push(format!(".{compressed_extension}"));
+    let compressed_path = PathBuf::from(compressed_path);
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let writer = BufWriter::new(File::create(&compressed_path)?);
+
+    match compression {
+        LogCompression::None => unreachable!("Uncompressed logs return early"),
+        LogCompression::Gzip => {
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+
+            io::copy(&mut reader, &mut encoder)?;
+            encoder.finish()?.flush()?;
+        }
+        LogCompression::Zstd => {
+            let mut encoder = zstd::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+
+            io::copy(&mut reader, &mut encoder)?;
+            encoder.finish()?.flush()?;
+        }
+    }
+
+    fs::remove_file(path)?;
+
+    Ok(Some(compressed_path))
+}
+
+fn remove_session(session: &LogSession) -> Result<(), ProcessOutputLogError> {
+    for file in &session.files {
+        fs::remove_file(file)
+            .map_err(|error| ProcessOutputLogError::RemoveLog(error, file.clone()))?;
+    }
+
+    Ok(())
+}
+
+// Removes the sessions that are over the count or age limit, compresses the older ones that are
+// kept and then removes the oldest until the total size fits. The newest session is never removed.
+pub fn prune_sessions(
+    game_log_directory: &Path,
+    retention: &LogRetention,
+    now: NaiveDateTime,
+) -> Result<PruneReport, ProcessOutputLogError> {
+    let mut report = PruneReport::default();
+    let mut kept_sessions = Vec::new();
+
+    let oldest_allowed = retention
+        .max_age_days
+        .map(|max_age_days| now - Duration::days(i64::from(max_age_days)));
+
+    for (index, session) in find_sessions(game_log_directory)?.into_iter().enumerate() {
+        let over_count = retention
+            .max_sessions
+            .is_some_and(|max_sessions| index >= max_sessions.max(1));
+        let too_old =
+            oldest_allowed.is_some_and(|oldest_allowed| session.timestamp < oldest_allowed);
+
+        if index > 0 && (over_count || too_old) {
+            report.freed_bytes += session.size()?;
+            remove_session(&session)?;
+            report.removed_sessions += 1;
+        } else {
+            kept_sessions.push(session);
+        }
+    }
+
+    for session in kept_sessions
+        .iter_mut()
+        .skip(retention.uncompressed_sessions)
+    {
+        for file in session.files.iter_mut() {
+            if !is_uncompressed_log(file) {
+                continue;
+            }
+
+            if let Some(compressed_file) = compress_file(file, retention.compression)
+                .map_err(|error| ProcessOutputLogError::CompressLog(error, file.clone()))?
+            {
+                *file = compressed_file;
+                report.compressed_files += 1;
+            }
+        }
+    }
+
+    if let Some(max_total_size) = retention.max_total_size_bytes() {
+        let mut total_size = 0;
+        let mut sizes = Vec::with_capacity(kept_sessions.len());
+
+        for session in &kept_sessions {
+            let size = session.size()?;
+
+            total_size += size;
+            sizes.push(size);
+        }
+
+        while total_size > max_total_size && kept_sessions.len() > 1 {
+            let (Some(session), Some(size)) = (kept_sessions.pop(), sizes.pop()) else {
+                break;
+            };
+
+            remove_session(&session)?;
+            total_size -= size;
+            report.freed_bytes += size;
+            report.removed_sessions += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prune_sessions, PruneReport};
+    use crate::{
+        config::{LogCompression, LogRetention},
+        process_output_log::{find_sessions, READABLE_TIMESTAMP_FORMAT},
+    };
+    use chrono::NaiveDateTime;
+    use color_eyre::eyre;
+    use std::{fs, io::Read, path::Path};
+    use tempdir::TempDir;
+
+    fn timestamp(timestamp: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(timestamp, READABLE_TIMESTAMP_FORMAT)
+            .expect("Invalid test timestamp")
+    }
+
+    fn write_session(directory: &Path, timestamp: &str, size: usize) -> eyre::Result<()> {
+        fs::write(
+            directory.join(format!("{timestamp}.outlog")),
+            "stdout line\n".repeat(size / 12),
+        )?;
+        fs::write(directory.join(format!("{timestamp}.errlog")), "")?;
+
+        Ok(())
+    }
+
+    fn file_names(directory: &Path) -> eyre::Result<Vec<String>> {
+        let mut names = fs::read_dir(directory)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<eyre::Result<Vec<String>>>()?;
+
+        names.sort();
+
+        Ok(names)
+    }
+
+    #[test]
+    fn prune_by_count_and_age_and_compress_older_sessions() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("prune_by_count_and_age_and_compress_older_sessions")?;
+        let directory = temp_dir.path();
+
+        for session in [
+            "2024-01-01_10:00:00",
+            "2024-05-01_10:00:00",
+            "2024-05-20_10:00:00",
+            "2024-05-30_10:00:00",
+            "2024-06-01_10:00:00",
+        ] {
+            write_session(directory, session, 1200)?;
+        }
+
+        fs::write(directory.join("notes.txt"), "not a log")?;
+
+        assert_eq!(find_sessions(directory)?.len(), 5);
+
+        let retention = LogRetention {
+            max_sessions: Some(4),
+            max_total_size_mib: None,
+            max_age_days: Some(30),
+            compression: LogCompression::Zstd,
+            uncompressed_sessions: 1,
+        };
+
+        let report = prune_sessions(directory, &retention, timestamp("2024-06-02_10:00:00"))?;
+
+        assert_eq!(report.removed_sessions, 2);
+        assert_eq!(report.compressed_files, 4);
+        assert_eq!(
+            file_names(directory)?,
+            [
+                "2024-05-20_10:00:00.errlog.zst",
+                "2024-05-20_10:00:00.outlog.zst",
+                "2024-05-30_10:00:00.errlog.zst",
+                "2024-05-30_10:00:00.outlog.zst",
+                "2024-06-01_10:00:00.errlog",
+                "2024-06-01_10:00:00.outlog",
+                "notes.txt",
+            ]
+        );
+
+        let mut decompressed = String::new();
+
+        zstd::Decoder::new(fs::File::open(
+            directory.join("2024-05-30_10:00:00.outlog.zst"),
+        )?)?
+        .read_to_string(&mut decompressed)?;
+
+        assert_eq!(decompressed, "stdout line\n".repeat(100));
+
+        // Everything is already compressed or within the limits.
+        assert_eq!(
+            prune_sessions(directory, &retention, timestamp("2024-06-02_10:00:00"))?,
+            PruneReport::default()
+        );
+
+        // The newest session is kept even when it's too old.
+        let report = prune_sessions(directory, &retention, timestamp("2025-01-01_10:00:00"))?;
+
+        assert_eq!(report.removed_sessions, 2);
+        assert_eq!(find_sessions(directory)?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn prune_by_total_size_with_gzip() -> eyre::Result<()> {
+        let temp_dir = TempDir::new("prune_by_total_size_with_gzip")?;
+        let directory = temp_dir.path();
+
+        // Random-ish content, so that compression doesn't make every session fit.
+        let mut state: u64 = 1;
+        let noise: String = (0..600_000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+
+                char::from(b'a' + (state >> 59) as u8 % 26)
+            })
+            .collect();
+
+        for session in [
+            "2024-06-01_10:00:00",
+            "2024-06-02_10:00:00",
+            "2024-06-03_10:00:00",
+        ] {
+            fs::write(directory.join(format!("{session}.log")), &noise)?;
+        }
+
+        let retention = LogRetention {
+            max_sessions: None,
+            max_total_size_mib: Some(1),
+            max_age_days: None,
+            compression: LogCompression::Gzip,
+            uncompressed_sessions: 1,
+        };
+
+        let report = prune_sessions(directory, &retention, timestamp("2024-06-04_10:00:00"))?;
+
+        assert_eq!(report.compressed_files, 2);
+        assert_eq!(report.removed_sessions, 1);
+
+        let sessions = find_sessions(directory)?;
+        let total_size = sessions
+            .iter()
+            .map(|session| session.size())
+            .sum::<Result<u64, _>>()?;
+
+        assert!(total_size <= 1024 * 1024);
+        assert_eq!(sessions[0].timestamp, timestamp("2024-06-03_10:00:00"));
+        assert!(directory.join("2024-06-03_10:00:00.log").is_file());
+
+        Ok(())
+    }
+}
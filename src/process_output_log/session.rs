@@ -0,0 +1,411 @@
+use super::{ProcessOutputLogError, ProcessOutputLogKind, READABLE_TIMESTAMP_FORMAT};
+use chrono::{DateTime, Local, NaiveDateTime};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+const SESSION_METADATA_EXTENSION: &str = "session.json";
+const SESSION_EXIT_EXTENSION: &str = "exit";
+
+const LOG_KINDS: [ProcessOutputLogKind; 3] = [
+    ProcessOutputLogKind::Stdout,
+    ProcessOutputLogKind::Stderr,
+    ProcessOutputLogKind::Combined,
+];
+
+// Written next to the logs of a launch once the game exited, with everything needed to
+// reproduce it. Fields missing from older sessions are left at their defaults.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct SessionMetadata {
+    pub gtnkr_version: String,
+    pub app_id: String,
+    pub config_file: Option<PathBuf>,
+    // The game config after merging the defaults and profiles.
+    pub config: serde_json::Value,
+    pub command: Vec<String>,
+    // Only the variables gtnkr changed, `null` for removed ones.
+    pub environment: BTreeMap<String, Option<String>>,
+    pub started_at: Option<DateTime<Local>>,
+    pub ended_at: Option<DateTime<Local>>,
+    pub duration_secs: f64,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub system: SystemInfo,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct SystemInfo {
+    pub kernel: Option<String>,
+    pub compositor: Option<String>,
+    pub compositor_version: Option<String>,
+    pub gamescope_version: Option<String>,
+    pub gpus: Vec<GpuInfo>,
+    pub vulkan_drivers: Vec<VulkanDriverInfo>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct GpuInfo {
+    pub pci_address: String,
+    pub pci_id: String,
+    pub driver: Option<String>,
+    pub boot_vga: bool,
+    // Whether the game config's `gpu` selected it.
+    pub selected: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct VulkanDriverInfo {
+    pub driver: Option<String>,
+    pub api_version: Option<String>,
+    pub manifest_path: PathBuf,
+    pub library_path: PathBuf,
+}
+
+// Only written by older versions, as `code <n>` or `signal <n>`, the metadata has it now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionExit {
+    Code(i32),
+    Signal(i32),
+}
+
+impl SessionExit {
+    fn parse(contents: &str) -> Option<Self> {
+        let (kind, value) = contents.trim().split_once(' ')?;
+        let value = value.parse().ok()?;
+
+        match kind {
+            "code" => Some(Self::Code(value)),
+            "signal" => Some(Self::Signal(value)),
+            _ => None,
+        }
+    }
+}
+
+// Every file of a single launch, they share the timestamp the launch started at.
+#[derive(Debug, PartialEq)]
+pub struct LogSession {
+    pub timestamp: NaiveDateTime,
+    pub files: Vec<PathBuf>,
+}
+
+fn strip_compression_extension(file_name: &str) -> &str {
+    file_name
+        .strip_suffix(".zst")
+        .or_else(|| file_name.strip_suffix(".gz"))
+        .unwrap_or(file_name)
+}
+
+fn log_kind(path: &Path) -> Option<ProcessOutputLogKind> {
+    let file_name = strip_compression_extension(path.file_name()?.to_str()?);
+    let (_, extension) = file_name.split_once('.')?;
+
+    LOG_KINDS
+        .into_iter()
+        .find(|kind| kind.as_file_extension() == extension)
+}
+
+pub(super) fn is_uncompressed_log(path: &Path) -> bool {
+    log_kind(path).is_some()
+        && path
+            .extension()
+            .is_some_and(|extension| extension != "zst" && extension != "gz")
+}
+
+impl LogSession {
+    pub fn size(&self) -> Result<u64, ProcessOutputLogError> {
+        self.files.iter().try_fold(0, |size, file| {
+            let metadata = fs::metadata(file)
+                .map_err(|error| ProcessOutputLogError::ReadLogDirectory(error, file.clone()))?;
+
+            Ok(size + metadata.len())
+        })
+    }
+
+    // The log of a kind, compressed or not.
+    pub fn log_file(&self, kind: ProcessOutputLogKind) -> Option<&Path> {
+        self.files
+            .iter()
+            .find(|file| log_kind(file) == Some(kind))
+            .map(PathBuf::as_path)
+    }
+
+    pub fn log_files(&self) -> impl Iterator<Item = &Path> {
+        self.files
+            .iter()
+            .filter(|file| log_kind(file).is_some())
+            .map(PathBuf::as_path)
+    }
+
+    // `None` for sessions from before the metadata was written, or that are still running.
+    pub fn metadata(&self) -> Result<Option<SessionMetadata>, ProcessOutputLogError> {
+        let Some(path) = self.files.iter().find(|file| {
+            file.file_name()
+                .and_then(|file_name| file_name.to_str())
+                .is_some_and(|file_name| file_name.ends_with(SESSION_METADATA_EXTENSION))
+        }) else {
+            return Ok(None);
+        };
+
+        let metadata =
+            fs::read(path).map_err(|error| ProcessOutputLogError::ReadLog(error, path.clone()))?;
+
+        serde_json::from_slice(&metadata)
+            .map(Some)
+            .map_err(|error| ProcessOutputLogError::ParseSessionMetadata(error, path.clone()))
+    }
+
+    // From the metadata, or the `.exit` file of sessions from before it was written.
+    pub fn exit(&self) -> Result<Option<SessionExit>, ProcessOutputLogError> {
+        if let Some(metadata) = self.metadata()? {
+            return Ok(match (metadata.exit_code, metadata.signal) {
+                (Some(exit_code), _) => Some(SessionExit::Code(exit_code)),
+                (None, Some(signal)) => Some(SessionExit::Signal(signal)),
+                (None, None) => None,
+            });
+        }
+
+        let Some(path) = self.files.iter().find(|file| {
+            file.extension()
+                .is_some_and(|extension| extension == SESSION_EXIT_EXTENSION)
+        }) else {
+            return Ok(None);
+        };
+
+        let contents = fs::read_to_string(path)
+            .map_err(|error| ProcessOutputLogError::ReadLog(error, path.clone()))?;
+
+        SessionExit::parse(&contents)
+            .map(Some)
+            .ok_or_else(|| ProcessOutputLogError::ParseSessionExit(contents, path.clone()))
+    }
+}
+
+fn session_metadata_path(game_log_directory: &Path, timestamp: &NaiveDateTime) -> PathBuf {
+    game_log_directory.join(format!(
+        "{}.{SESSION_METADATA_EXTENSION}",
+        timestamp.format(READABLE_TIMESTAMP_FORMAT)
+    ))
+}
+
+pub fn write_session_metadata(
+    game_log_directory: &Path,
+    timestamp: &NaiveDateTime,
+    metadata: &SessionMetadata,
+) -> Result<(), ProcessOutputLogError> {
+    let path = session_metadata_path(game_log_directory, timestamp);
+    let metadata = serde_json::to_vec_pretty(metadata)
+        .expect("Session metadata should always be serializable");
+
+    fs::write(&path, metadata)
+        .map_err(|error| ProcessOutputLogError::CreateOutputLogFile(error, path))
+}
+
+// Everything before the first `.` is the timestamp, files that don't start with one are
+// ignored.
+fn session_timestamp(path: &Path) -> Option<NaiveDateTime> {
+    let file_name = path.file_name()?.to_str()?;
+    let (timestamp, _) = file_name.split_once('.')?;
+
+    NaiveDateTime::parse_from_str(timestamp, READABLE_TIMESTAMP_FORMAT).ok()
+}
+
+// The sessions in a game's log directory, newest first.
+pub fn find_sessions(game_log_directory: &Path) -> Result<Vec<LogSession>, ProcessOutputLogError> {
+    let entries = match fs::read_dir(game_log_directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(ProcessOutputLogError::ReadLogDirectory(
+                error,
+                game_log_directory.to_path_buf(),
+            ))
+        }
+    };
+
+    let mut paths = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()
+        .map_err(|error| {
+            ProcessOutputLogError::ReadLogDirectory(error, game_log_directory.to_path_buf())
+        })?;
+
+    paths.sort();
+
+    let mut sessions: Vec<LogSession> = Vec::new();
+
+    for path in paths {
+        let Some(timestamp) = session_timestamp(&path) else {
+            continue;
+        };
+
+        match sessions.last_mut() {
+            Some(session) if session.timestamp == timestamp => session.files.push(path),
+            _ => sessions.push(LogSession {
+                timestamp,
+                files: vec![path],
+            }),
+        }
+    }
+
+    sessions.reverse();
+
+    Ok(sessions)
+}
+
+// The identifiers (app IDs) that have a log directory in the base log directory.
+pub fn find_logged_identifiers(
+    base_log_directory: &Path,
+) -> Result<Vec<String>, ProcessOutputLogError> {
+    let entries = match fs::read_dir(base_log_directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(ProcessOutputLogError::ReadLogDirectory(
+                error,
+                base_log_directory.to_path_buf(),
+            ))
+        }
+    };
+
+    let mut identifiers = Vec::new();
+
+    for entry in entries {
+        let entry = entry.map_err(|error| {
+            ProcessOutputLogError::ReadLogDirectory(error, base_log_directory.to_path_buf())
+        })?;
+
+        if entry.path().is_dir() {
+            identifiers.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    identifiers.sort();
+
+    Ok(identifiers)
+}
+
+// Decompresses `.zst` and `.gz` logs, other files are read as they are.
+pub fn open_log(path: &Path) -> Result<Box<dyn Read>, ProcessOutputLogError> {
+    let read_error = |error| ProcessOutputLogError::ReadLog(error, path.to_path_buf());
+    let file = BufReader::new(File::open(path).map_err(read_error)?);
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("zst") => Ok(Box::new(
+            zstd::Decoder::with_buffer(file).map_err(read_error)?,
+        )),
+        Some("gz") => Ok(Box::new(GzDecoder::new(file))),
+        _ => Ok(Box::new(file)),
+    }
+}
+
+pub fn read_log_to_string(path: &Path) -> Result<String, ProcessOutputLogError> {
+    let mut contents = Vec::new();
+
+    open_log(path)?
+        .read_to_end(&mut contents)
+        .map_err(|error| ProcessOutputLogError::ReadLog(error, path.to_path_buf()))?;
+
+    Ok(String::from_utf8_lossy(&contents).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        find_sessions, read_log_to_string, write_session_metadata, LogSession, SessionExit,
+        SessionMetadata,
+    };
+    use crate::config::{LogCompression, LogRetention};
+    use crate::process_output_log::{
+        prune_sessions, ProcessOutputLogKind, READABLE_TIMESTAMP_FORMAT,
+    };
+    use chrono::NaiveDateTime;
+    use color_eyre::eyre;
+    use std::fs;
+    use tempdir::TempDir;
+
+    #[test]
+    fn read_compressed_sessions_and_their_metadata() -> eyre::Result<()> {
+        let older =
+            NaiveDateTime::parse_from_str("2024-06-01_10:00:00", READABLE_TIMESTAMP_FORMAT)?;
+        let metadata = SessionMetadata {
+            app_id: String::from("570"),
+            signal: Some(9),
+            ..SessionMetadata::default()
+        };
+
+        for compression in [LogCompression::Gzip, LogCompression::Zstd] {
+            let temp_dir = TempDir::new("read_compressed_sessions_and_their_metadata")?;
+            let directory = temp_dir.path();
+
+            for timestamp in ["2024-06-01_10:00:00", "2024-06-02_10:00:00"] {
+                fs::write(
+                    directory.join(format!("{timestamp}.errlog")),
+                    "0024:err:test\n",
+                )?;
+            }
+
+            write_session_metadata(directory, &older, &metadata)?;
+
+            let retention = LogRetention {
+                compression,
+                ..LogRetention::default()
+            };
+
+            prune_sessions(directory, &retention, older)?;
+
+            let sessions = find_sessions(directory)?;
+            let errlog = sessions[1]
+                .log_file(ProcessOutputLogKind::Stderr)
+                .expect("The errlog should've been found");
+
+            assert_ne!(errlog.extension(), Some("errlog".as_ref()));
+            assert_eq!(read_log_to_string(errlog)?, "0024:err:test\n");
+            assert_eq!(sessions[1].log_files().count(), 1);
+            assert_eq!(sessions[1].metadata()?, Some(metadata.clone()));
+            assert_eq!(sessions[0].metadata()?, None);
+            assert_eq!(sessions[1].exit()?, Some(SessionExit::Signal(9)));
+        }
+
+        // Sessions from before the metadata had every field.
+        let temp_dir = TempDir::new("read_compressed_sessions_and_their_metadata")?;
+        let path = temp_dir.path().join("2024-06-01_10:00:00.session.json");
+
+        fs::write(&path, r#"{ "exit_code": 3, "signal": null }"#)?;
+
+        let session = LogSession {
+            timestamp: older,
+            files: vec![path],
+        };
+
+        assert_eq!(
+            session.metadata()?,
+            Some(SessionMetadata {
+                exit_code: Some(3),
+                ..SessionMetadata::default()
+            })
+        );
+
+        // Sessions from before the metadata only had their exit status.
+        let path = temp_dir.path().join("2024-06-02_10:00:00.exit");
+
+        fs::write(&path, "signal 6")?;
+
+        let session = LogSession {
+            timestamp: older,
+            files: vec![path],
+        };
+
+        assert_eq!(session.exit()?, Some(SessionExit::Signal(6)));
+
+        Ok(())
+    }
+}
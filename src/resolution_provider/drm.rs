@@ -0,0 +1,152 @@
+use super::{Output, ResolutionProvider, ResolutionProviderError};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const SYSFS_DRM_DIRECTORY: &str = "/sys/class/drm";
+
+// Works without a compositor, but only knows the preferred mode of each connector and not which
+// one is focused or its refresh rate.
+pub struct DrmProvider {
+    sysfs_directory: PathBuf,
+}
+
+impl Default for DrmProvider {
+    fn default() -> Self {
+        Self::new(PathBuf::from(SYSFS_DRM_DIRECTORY))
+    }
+}
+
+impl DrmProvider {
+    pub fn new(sysfs_directory: PathBuf) -> Self {
+        Self { sysfs_directory }
+    }
+}
+
+fn read_attribute(connector_path: &Path, attribute: &str) -> io::Result<Option<String>> {
+    match fs::read_to_string(connector_path.join(attribute)) {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+// The first line of `modes` is the preferred mode, e.g. `2560x1440` or `1920x1080i`.
+fn parse_mode(mode: &str) -> Option<(u16, u16)> {
+    let (width, height) = mode.trim().split_once('x')?;
+    let height = height.trim_end_matches(|character: char| !character.is_ascii_digit());
+
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+impl ResolutionProvider for DrmProvider {
+    fn name(&self) -> &'static str {
+        "DRM"
+    }
+
+    fn outputs(&self) -> Result<Vec<Output>, ResolutionProviderError> {
+        let read_error =
+            |error| ResolutionProviderError::ReadSysfs(error, self.sysfs_directory.clone());
+
+        let mut connector_paths = fs::read_dir(&self.sysfs_directory)
+            .map_err(read_error)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<PathBuf>>>()
+            .map_err(read_error)?;
+
+        connector_paths.sort();
+
+        let mut outputs = Vec::new();
+
+        for connector_path in connector_paths {
+            // Connectors are named `card<N>-<output>`, e.g. `card1-DP-1`.
+            let Some(name) = connector_path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .filter(|file_name| file_name.starts_with("card"))
+                .and_then(|file_name| file_name.split_once('-'))
+                .map(|(_, name)| name.to_string())
+            else {
+                continue;
+            };
+
+            let read = |attribute| {
+                read_attribute(&connector_path, attribute).map_err(|error| {
+                    ResolutionProviderError::ReadSysfs(error, connector_path.clone())
+                })
+            };
+
+            let connected = read("status")?.is_some_and(|status| status.trim() == "connected");
+            let enabled = read("enabled")?.as_deref().unwrap_or("enabled").trim() == "enabled";
+
+            if !connected || !enabled {
+                continue;
+            }
+
+            let Some((width, height)) = read("modes")?
+                .as_deref()
+                .and_then(|modes| modes.lines().next())
+                .and_then(parse_mode)
+            else {
+                continue;
+            };
+
+            outputs.push(Output {
+                name,
+                width,
+                height,
+                refresh_rate: None,
+                focused: false,
+            });
+        }
+
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DrmProvider;
+    use crate::resolution_provider::{Output, ResolutionProvider};
+    use std::path::PathBuf;
+
+    #[test]
+    fn read_drm_connectors_from_sysfs() {
+        let provider = DrmProvider::new(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/resolution_providers/drm"),
+        );
+
+        let outputs = provider.outputs().expect("Failed to read the fixture");
+
+        assert_eq!(
+            outputs,
+            vec![
+                Output {
+                    name: String::from("DP-1"),
+                    width: 2560,
+                    height: 1440,
+                    refresh_rate: None,
+                    focused: false,
+                },
+                Output {
+                    name: String::from("HDMI-A-1"),
+                    width: 3840,
+                    height: 2160,
+                    refresh_rate: None,
+                    focused: false,
+                },
+            ]
+        );
+        assert_eq!(
+            provider.focused_output().map(|output| output.name).ok(),
+            Some(String::from("DP-1"))
+        );
+        assert_eq!(
+            provider.output("HDMI-A-1").map(|output| output.width).ok(),
+            Some(3840)
+        );
+        assert!(provider.output("DP-2").is_err());
+    }
+}
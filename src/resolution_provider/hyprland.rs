@@ -0,0 +1,69 @@
+use super::{with_timeout, Output, ResolutionProvider, ResolutionProviderError};
+use hyprland::{
+    data::{Monitor as HyprlandMonitor, Monitors as HyprlandMonitors},
+    shared::HyprData,
+};
+
+pub struct HyprlandProvider;
+
+impl ResolutionProvider for HyprlandProvider {
+    fn name(&self) -> &'static str {
+        "Hyprland"
+    }
+
+    fn outputs(&self) -> Result<Vec<Output>, ResolutionProviderError> {
+        let monitors = with_timeout(self.name(), || {
+            HyprlandMonitors::get().map_err(ResolutionProviderError::Hyprland)
+        })?;
+
+        Ok(monitors.into_iter().map(Output::from).collect())
+    }
+}
+
+impl From<HyprlandMonitor> for Output {
+    fn from(monitor: HyprlandMonitor) -> Self {
+        Self {
+            name: monitor.name,
+            width: monitor.width,
+            height: monitor.height,
+            refresh_rate: Some(monitor.refresh_rate),
+            focused: monitor.focused,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HyprlandMonitor;
+    use crate::resolution_provider::Output;
+
+    #[test]
+    fn parse_hyprctl_monitors() {
+        let monitors: Vec<HyprlandMonitor> = serde_json::from_str(include_str!(
+            "../../tests/fixtures/resolution_providers/hyprland_monitors.json"
+        ))
+        .expect("Failed to parse the fixture");
+
+        let outputs: Vec<Output> = monitors.into_iter().map(Output::from).collect();
+
+        assert_eq!(
+            outputs,
+            vec![
+                Output {
+                    name: String::from("DP-1"),
+                    width: 2560,
+                    height: 1440,
+                    refresh_rate: Some(143.998),
+                    focused: false,
+                },
+                Output {
+                    name: String::from("HDMI-A-1"),
+                    width: 3840,
+                    height: 2160,
+                    refresh_rate: Some(119.88),
+                    focused: true,
+                },
+            ]
+        );
+    }
+}
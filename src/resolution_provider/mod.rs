@@ -0,0 +1,344 @@
+mod drm;
+mod hyprland;
+mod sway;
+mod wlr_randr;
+mod xrandr;
+
+pub use drm::DrmProvider;
+pub use hyprland::HyprlandProvider;
+pub use sway::SwayProvider;
+pub use wlr_randr::WlrRandrProvider;
+pub use xrandr::XrandrProvider;
+
+use std::{
+    env,
+    io::{self, Read},
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use which::which;
+
+// A compositor or tool that doesn't answer in time fails the lookup instead of keeping gtnkr
+// from launching the game.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResolutionProviderError {
+    #[error("Failed to query the monitors from Hyprland, see: {0}")]
+    Hyprland(::hyprland::shared::HyprError),
+
+    #[error(
+        "IO error while attempting to query the outputs from the Sway socket `{1:#?}`, see: {0:#?}"
+    )]
+    SwayIpc(io::Error, PathBuf),
+
+    #[error("Failed to run `{1}`, see: {0:#?}")]
+    RunCommand(io::Error, String),
+
+    #[error("`{0}` didn't answer within {1:?}")]
+    TimedOut(String, Duration),
+
+    #[error("`{0}` exited with {1}: {2}")]
+    CommandFailed(String, std::process::ExitStatus, String),
+
+    #[error("Failed to parse the outputs reported by {0}, see: {1}")]
+    ParseOutputs(&'static str, String),
+
+    #[error("IO error while attempting to read the DRM connectors in `{1:#?}`, see: {0:#?}")]
+    ReadSysfs(io::Error, PathBuf),
+
+    #[error("{0} didn't report any enabled outputs")]
+    NoOutputs(&'static str),
+
+    #[error(
+        "Can't scale the resolution by {0}, the factor has to be greater than 0 and at most 1"
+    )]
+    InvalidScale(f32),
+
+    #[error("{0} didn't report an enabled output named `{1}`, the available outputs are: {}", .2.join(", "))]
+    MissingOutput(&'static str, String, Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub name: String,
+    pub width: u16,
+    pub height: u16,
+    // In Hz, not every backend knows it.
+    pub refresh_rate: Option<f32>,
+    pub focused: bool,
+}
+
+pub trait ResolutionProvider {
+    fn name(&self) -> &'static str;
+
+    // The enabled outputs, in the order the backend reports them.
+    fn outputs(&self) -> Result<Vec<Output>, ResolutionProviderError>;
+
+    // Backends that don't know which output is focused return the first one (or the primary one
+    // in case of xrandr).
+    fn focused_output(&self) -> Result<Output, ResolutionProviderError> {
+        let mut outputs = self.outputs()?;
+
+        match outputs.iter().position(|output| output.focused) {
+            Some(index) => Ok(outputs.swap_remove(index)),
+            None if !outputs.is_empty() => Ok(outputs.swap_remove(0)),
+            None => Err(ResolutionProviderError::NoOutputs(self.name())),
+        }
+    }
+
+    fn output(&self, name: &str) -> Result<Output, ResolutionProviderError> {
+        let mut outputs = self.outputs()?;
+
+        match outputs.iter().position(|output| output.name == name) {
+            Some(index) => Ok(outputs.swap_remove(index)),
+            None => Err(ResolutionProviderError::MissingOutput(
+                self.name(),
+                name.to_string(),
+                outputs.into_iter().map(|output| output.name).collect(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolutionProviderKind {
+    Hyprland,
+    Sway(PathBuf),
+    WlrRandr,
+    Xrandr,
+    Drm,
+}
+
+impl ResolutionProviderKind {
+    pub fn detect() -> Self {
+        Self::detect_with(
+            |variable| env::var(variable).ok().filter(|value| !value.is_empty()),
+            |program| which(program).is_ok(),
+        )
+    }
+
+    fn detect_with(
+        get_env: impl Fn(&str) -> Option<String>,
+        has_program: impl Fn(&str) -> bool,
+    ) -> Self {
+        let current_desktop = get_env("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let desktops: Vec<&str> = current_desktop.split(':').collect();
+
+        if get_env("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            return Self::Hyprland;
+        }
+
+        if let Some(socket_path) = get_env("SWAYSOCK") {
+            return Self::Sway(PathBuf::from(socket_path));
+        }
+
+        let is_wayland = get_env("WAYLAND_DISPLAY").is_some()
+            || get_env("XDG_SESSION_TYPE").is_some_and(|session_type| session_type == "wayland");
+
+        // Without a Wayland compositor xrandr talks to the X server, with one it'd only see
+        // Xwayland's outputs.
+        if !is_wayland && get_env("DISPLAY").is_some() && has_program("xrandr") {
+            return Self::Xrandr;
+        }
+
+        // GNOME and KDE don't implement wlr-output-management.
+        let supports_wlr_output_management = !desktops
+            .iter()
+            .any(|desktop| ["gnome", "kde", "unity"].contains(desktop));
+
+        if is_wayland && supports_wlr_output_management && has_program("wlr-randr") {
+            return Self::WlrRandr;
+        }
+
+        Self::Drm
+    }
+
+    pub fn into_provider(self) -> Box<dyn ResolutionProvider> {
+        match self {
+            Self::Hyprland => Box::new(HyprlandProvider),
+            Self::Sway(socket_path) => Box::new(SwayProvider::new(socket_path)),
+            Self::WlrRandr => Box::new(WlrRandrProvider),
+            Self::Xrandr => Box::new(XrandrProvider),
+            Self::Drm => Box::new(DrmProvider::default()),
+        }
+    }
+}
+
+pub fn detect_resolution_provider() -> Box<dyn ResolutionProvider> {
+    let kind = ResolutionProviderKind::detect();
+
+    tracing::debug!("Using the {kind:?} resolution provider");
+
+    kind.into_provider()
+}
+
+// Runs the query on its own thread, it's left behind if it doesn't return in time.
+fn with_timeout<T, F>(name: &str, query: F) -> Result<T, ResolutionProviderError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, ResolutionProviderError> + Send + 'static,
+{
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    thread::spawn(move || {
+        let _ = sender.send(query());
+    });
+
+    receiver
+        .recv_timeout(QUERY_TIMEOUT)
+        .map_err(|_| ResolutionProviderError::TimedOut(name.to_string(), QUERY_TIMEOUT))?
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut contents = Vec::new();
+
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut contents);
+        }
+
+        contents
+    })
+}
+
+fn run_command(program: &str, arguments: &[&str]) -> Result<String, ResolutionProviderError> {
+    run_command_within(program, arguments, QUERY_TIMEOUT)
+}
+
+// The program is killed if it doesn't exit in time.
+fn run_command_within(
+    program: &str,
+    arguments: &[&str],
+    timeout: Duration,
+) -> Result<String, ResolutionProviderError> {
+    let run_error = |error| ResolutionProviderError::RunCommand(error, program.to_string());
+
+    let mut child = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(run_error)?;
+
+    // Read while waiting, so that a full pipe can't keep the program from exiting.
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(run_error)? {
+            break status;
+        }
+
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+
+            return Err(ResolutionProviderError::TimedOut(
+                program.to_string(),
+                timeout,
+            ));
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        return Err(ResolutionProviderError::CommandFailed(
+            program.to_string(),
+            status,
+            String::from_utf8_lossy(&stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_command, run_command_within, ResolutionProviderError, ResolutionProviderKind};
+    use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+    fn detect(variables: &[(&str, &str)], programs: &[&str]) -> ResolutionProviderKind {
+        let variables: HashMap<&str, &str> = variables.iter().copied().collect();
+
+        ResolutionProviderKind::detect_with(
+            |variable| variables.get(variable).map(|value| value.to_string()),
+            |program| programs.contains(&program),
+        )
+    }
+
+    #[test]
+    fn detect_the_backend_from_the_environment() {
+        assert_eq!(
+            detect(
+                &[
+                    ("XDG_CURRENT_DESKTOP", "Hyprland"),
+                    ("HYPRLAND_INSTANCE_SIGNATURE", "abc"),
+                    ("WAYLAND_DISPLAY", "wayland-1"),
+                ],
+                &["wlr-randr"]
+            ),
+            ResolutionProviderKind::Hyprland
+        );
+        assert_eq!(
+            detect(&[("SWAYSOCK", "/run/user/1000/sway-ipc.sock")], &[]),
+            ResolutionProviderKind::Sway(PathBuf::from("/run/user/1000/sway-ipc.sock"))
+        );
+        assert_eq!(
+            detect(
+                &[
+                    ("XDG_CURRENT_DESKTOP", "river"),
+                    ("WAYLAND_DISPLAY", "wayland-1")
+                ],
+                &["wlr-randr", "xrandr"]
+            ),
+            ResolutionProviderKind::WlrRandr
+        );
+        assert_eq!(
+            detect(
+                &[
+                    ("XDG_CURRENT_DESKTOP", "KDE"),
+                    ("WAYLAND_DISPLAY", "wayland-0"),
+                    ("DISPLAY", ":1")
+                ],
+                &["wlr-randr", "xrandr"]
+            ),
+            ResolutionProviderKind::Drm
+        );
+        assert_eq!(
+            detect(
+                &[("XDG_CURRENT_DESKTOP", "XFCE"), ("DISPLAY", ":0")],
+                &["xrandr"]
+            ),
+            ResolutionProviderKind::Xrandr
+        );
+    }
+
+    #[test]
+    fn report_failing_and_hanging_commands() {
+        assert_eq!(
+            run_command("sh", &["-c", "echo output"]).ok(),
+            Some(String::from("output\n"))
+        );
+        assert!(matches!(
+            run_command("sh", &["-c", "echo error >&2; exit 3"]),
+            Err(ResolutionProviderError::CommandFailed(_, _, error)) if error == "error"
+        ));
+        assert!(matches!(
+            run_command_within("sleep", &["10"], Duration::from_millis(100)),
+            Err(ResolutionProviderError::TimedOut(..))
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+use super::{Output, ResolutionProvider, ResolutionProviderError, QUERY_TIMEOUT};
+use serde::Deserialize;
+use std::{
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+};
+
+const IPC_MAGIC: &[u8; 6] = b"i3-ipc";
+const IPC_GET_OUTPUTS: u32 = 3;
+
+pub struct SwayProvider {
+    socket_path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct SwayOutput {
+    name: String,
+    active: bool,
+    focused: bool,
+    current_mode: Option<SwayMode>,
+}
+
+#[derive(Deserialize)]
+struct SwayMode {
+    width: u16,
+    height: u16,
+    // In mHz.
+    refresh: u32,
+}
+
+impl SwayProvider {
+    pub fn new(socket_path: PathBuf) -> Self {
+        Self { socket_path }
+    }
+
+    fn request(&self, message_type: u32) -> io::Result<Vec<u8>> {
+        let mut stream = UnixStream::connect(&self.socket_path)?;
+
+        stream.set_read_timeout(Some(QUERY_TIMEOUT))?;
+        stream.set_write_timeout(Some(QUERY_TIMEOUT))?;
+
+        // An i3 IPC message is the magic string followed by the payload length and the message
+        // type, both in native byte order, and the payload.
+        let mut message = Vec::from(*IPC_MAGIC);
+
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+
+        stream.write_all(&message)?;
+
+        let mut header = [0; 14];
+
+        stream.read_exact(&mut header)?;
+
+        if &header[..6] != IPC_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The reply doesn't start with the i3 IPC magic string",
+            ));
+        }
+
+        let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+        let mut payload = vec![0; length as usize];
+
+        stream.read_exact(&mut payload)?;
+
+        Ok(payload)
+    }
+}
+
+impl ResolutionProvider for SwayProvider {
+    fn name(&self) -> &'static str {
+        "Sway"
+    }
+
+    fn outputs(&self) -> Result<Vec<Output>, ResolutionProviderError> {
+        let payload = self
+            .request(IPC_GET_OUTPUTS)
+            .map_err(|error| ResolutionProviderError::SwayIpc(error, self.socket_path.clone()))?;
+
+        parse_outputs(&String::from_utf8_lossy(&payload))
+    }
+}
+
+fn parse_outputs(json: &str) -> Result<Vec<Output>, ResolutionProviderError> {
+    let outputs: Vec<SwayOutput> = serde_json::from_str(json)
+        .map_err(|error| ResolutionProviderError::ParseOutputs("Sway", error.to_string()))?;
+
+    Ok(outputs
+        .into_iter()
+        .filter(|output| output.active)
+        .filter_map(|output| {
+            let mode = output.current_mode?;
+
+            Some(Output {
+                name: output.name,
+                width: mode.width,
+                height: mode.height,
+                refresh_rate: Some(mode.refresh as f32 / 1000.0),
+                focused: output.focused,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_outputs;
+    use crate::resolution_provider::Output;
+
+    #[test]
+    fn parse_sway_get_outputs() {
+        let outputs = parse_outputs(include_str!(
+            "../../tests/fixtures/resolution_providers/sway_outputs.json"
+        ))
+        .expect("Failed to parse the fixture");
+
+        assert_eq!(
+            outputs,
+            vec![
+                Output {
+                    name: String::from("DP-1"),
+                    width: 2560,
+                    height: 1440,
+                    refresh_rate: Some(143.998),
+                    focused: true,
+                },
+                Output {
+                    name: String::from("HDMI-A-1"),
+                    width: 3840,
+                    height: 2160,
+                    refresh_rate: Some(119.88),
+                    focused: false,
+                },
+            ]
+        );
+    }
+}
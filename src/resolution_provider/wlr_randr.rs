@@ -0,0 +1,86 @@
+use super::{run_command, Output, ResolutionProvider, ResolutionProviderError};
+use serde::Deserialize;
+
+// wlr-randr is the reference client of the wlr-output-management protocol, which is implemented
+// by most wlroots based compositors.
+pub struct WlrRandrProvider;
+
+#[derive(Deserialize)]
+struct WlrRandrOutput {
+    name: String,
+    enabled: bool,
+    modes: Vec<WlrRandrMode>,
+}
+
+#[derive(Deserialize)]
+struct WlrRandrMode {
+    width: u16,
+    height: u16,
+    refresh: f32,
+    current: bool,
+}
+
+impl ResolutionProvider for WlrRandrProvider {
+    fn name(&self) -> &'static str {
+        "wlr-randr"
+    }
+
+    fn outputs(&self) -> Result<Vec<Output>, ResolutionProviderError> {
+        parse_outputs(&run_command("wlr-randr", &["--json"])?)
+    }
+}
+
+fn parse_outputs(json: &str) -> Result<Vec<Output>, ResolutionProviderError> {
+    let outputs: Vec<WlrRandrOutput> = serde_json::from_str(json)
+        .map_err(|error| ResolutionProviderError::ParseOutputs("wlr-randr", error.to_string()))?;
+
+    Ok(outputs
+        .into_iter()
+        .filter(|output| output.enabled)
+        .filter_map(|output| {
+            let mode = output.modes.into_iter().find(|mode| mode.current)?;
+
+            Some(Output {
+                name: output.name,
+                width: mode.width,
+                height: mode.height,
+                refresh_rate: Some(mode.refresh),
+                focused: false,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_outputs;
+    use crate::resolution_provider::Output;
+
+    #[test]
+    fn parse_wlr_randr_json() {
+        let outputs = parse_outputs(include_str!(
+            "../../tests/fixtures/resolution_providers/wlr_randr.json"
+        ))
+        .expect("Failed to parse the fixture");
+
+        assert_eq!(
+            outputs,
+            vec![
+                Output {
+                    name: String::from("DP-1"),
+                    width: 2560,
+                    height: 1440,
+                    refresh_rate: Some(143.998),
+                    focused: false,
+                },
+                Output {
+                    name: String::from("HDMI-A-1"),
+                    width: 3840,
+                    height: 2160,
+                    refresh_rate: Some(119.88),
+                    focused: false,
+                },
+            ]
+        );
+    }
+}
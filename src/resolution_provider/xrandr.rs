@@ -0,0 +1,98 @@
+use super::{run_command, Output, ResolutionProvider, ResolutionProviderError};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+pub struct XrandrProvider;
+
+lazy_static! {
+    // e.g. `HDMI-1 connected primary 3840x2160+2560+0 (normal left ...) 1600mm x 900mm`, outputs
+    // that are connected but disabled don't have a geometry.
+    static ref CONNECTED_OUTPUT: Regex =
+        Regex::new(r"^(\S+) connected( primary)? (\d+)x(\d+)\+\d+\+\d+").unwrap();
+
+    // e.g. `   3840x2160     60.00 +  119.88*   59.94`, the current refresh rate is marked with `*`.
+    static ref CURRENT_REFRESH_RATE: Regex = Regex::new(r"^\s+\S+\s.*?([\d.]+)\*").unwrap();
+}
+
+impl ResolutionProvider for XrandrProvider {
+    fn name(&self) -> &'static str {
+        "xrandr"
+    }
+
+    fn outputs(&self) -> Result<Vec<Output>, ResolutionProviderError> {
+        parse_outputs(&run_command("xrandr", &["--query"])?)
+    }
+}
+
+fn parse_outputs(query: &str) -> Result<Vec<Output>, ResolutionProviderError> {
+    let mut outputs: Vec<Output> = Vec::new();
+    let mut in_enabled_output = false;
+
+    for line in query.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            in_enabled_output = false;
+
+            let Some(captures) = CONNECTED_OUTPUT.captures(line) else {
+                continue;
+            };
+
+            let parse_dimension = |index: usize| {
+                captures[index].parse::<u16>().map_err(|error| {
+                    ResolutionProviderError::ParseOutputs("xrandr", format!("{error} in `{line}`"))
+                })
+            };
+
+            outputs.push(Output {
+                name: captures[1].to_string(),
+                width: parse_dimension(3)?,
+                height: parse_dimension(4)?,
+                refresh_rate: None,
+                focused: captures.get(2).is_some(),
+            });
+
+            in_enabled_output = true;
+        } else if in_enabled_output {
+            if let (Some(captures), Some(output)) =
+                (CURRENT_REFRESH_RATE.captures(line), outputs.last_mut())
+            {
+                output.refresh_rate = captures[1].parse().ok();
+            }
+        }
+    }
+
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_outputs;
+    use crate::resolution_provider::Output;
+
+    #[test]
+    fn parse_xrandr_query() {
+        let outputs = parse_outputs(include_str!(
+            "../../tests/fixtures/resolution_providers/xrandr.txt"
+        ))
+        .expect("Failed to parse the fixture");
+
+        assert_eq!(
+            outputs,
+            vec![
+                Output {
+                    name: String::from("DP-1"),
+                    width: 2560,
+                    height: 1440,
+                    refresh_rate: Some(143.97),
+                    focused: false,
+                },
+                Output {
+                    name: String::from("HDMI-1"),
+                    width: 3840,
+                    height: 2160,
+                    refresh_rate: Some(119.88),
+                    focused: true,
+                },
+            ]
+        );
+    }
+}
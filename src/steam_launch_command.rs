@@ -0,0 +1,411 @@
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SteamLaunchCommandError {
+    #[error("Failed to split the Steam launch command into arguments, see: {0}")]
+    SplitCommandString(shell_words::ParseError),
+
+    #[error("The launch command is empty")]
+    EmptyCommand,
+
+    #[error(
+        "The Steam launch command doesn't contain `SteamLaunch`, is it really Steam's %command%?"
+    )]
+    MissingSteamLaunch,
+
+    #[error("The Steam launch command doesn't contain a valid `AppId=<number>`")]
+    MissingAppId,
+
+    #[error("Expected `--` after `{0}` in the Steam launch command")]
+    MissingSeparator(String),
+
+    #[error("The Steam launch command doesn't contain the game's executable")]
+    MissingGameExecutable,
+}
+
+const SEPARATOR: &str = "--";
+
+// The steam-runtime (pressure-vessel) container the game is started in.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SteamRuntime {
+    pub entry_point: String,
+    // Everything up to and including the `--` that ends the entry point's own options.
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProtonInvocation {
+    pub executable: String,
+    pub verb: String,
+}
+
+// Steam's `%command%`, split into the parts it's made of, e.g.
+// reaper SteamLaunch AppId=<id> -- steam-launch-wrapper -- <runtime> --verb=waitforexitandrun -- proton waitforexitandrun <game> <arguments>
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SteamLaunchCommand {
+    // `reaper SteamLaunch AppId=<id> --`, everything Steam uses to track the game's processes.
+    pub reaper: Vec<String>,
+    pub app_id: u32,
+    // `steam-launch-wrapper --`, empty when Steam didn't add it.
+    pub launch_wrapper: Vec<String>,
+    pub runtime: Option<SteamRuntime>,
+    pub proton: Option<ProtonInvocation>,
+    pub game_executable: String,
+    pub game_arguments: Vec<String>,
+}
+
+impl SteamLaunchCommand {
+    pub fn parse(command: &str) -> Result<Self, SteamLaunchCommandError> {
+        let arguments =
+            shell_words::split(command).map_err(SteamLaunchCommandError::SplitCommandString)?;
+
+        Self::from_arguments(arguments)
+    }
+
+    pub fn from_arguments(arguments: Vec<String>) -> Result<Self, SteamLaunchCommandError> {
+        if arguments.is_empty() {
+            return Err(SteamLaunchCommandError::EmptyCommand);
+        }
+
+        let mut arguments = arguments.into_iter().peekable();
+
+        let mut reaper = Vec::new();
+
+        for argument in arguments.by_ref() {
+            let is_steam_launch = argument == "SteamLaunch";
+
+            reaper.push(argument);
+
+            if is_steam_launch {
+                break;
+            }
+        }
+
+        if reaper.last().map(String::as_str) != Some("SteamLaunch") {
+            return Err(SteamLaunchCommandError::MissingSteamLaunch);
+        }
+
+        let mut app_id = None;
+
+        for argument in arguments.by_ref() {
+            let is_separator = argument == SEPARATOR;
+
+            if let Some(value) = argument.strip_prefix("AppId=") {
+                app_id = value.parse::<u32>().ok();
+            }
+
+            reaper.push(argument);
+
+            if is_separator {
+                break;
+            }
+        }
+
+        let app_id = app_id.ok_or(SteamLaunchCommandError::MissingAppId)?;
+
+        if reaper.last().map(String::as_str) != Some(SEPARATOR) {
+            return Err(SteamLaunchCommandError::MissingSeparator(String::from(
+                "SteamLaunch",
+            )));
+        }
+
+        let mut launch_wrapper = Vec::new();
+
+        if let Some(wrapper) =
+            arguments.next_if(|argument| file_name(argument) == "steam-launch-wrapper")
+        {
+            launch_wrapper.push(wrapper);
+
+            match arguments.next_if_eq(SEPARATOR) {
+                Some(separator) => launch_wrapper.push(separator),
+                None => {
+                    return Err(SteamLaunchCommandError::MissingSeparator(
+                        launch_wrapper.remove(0),
+                    ))
+                }
+            }
+        }
+
+        let mut runtime = None;
+
+        if let Some(entry_point) = arguments.next_if(|argument| is_runtime_entry_point(argument)) {
+            let mut runtime_arguments = Vec::new();
+
+            // The legacy scout `run.sh` takes the command directly, without any options.
+            if file_name(&entry_point) != "run.sh" {
+                for argument in arguments.by_ref() {
+                    let is_separator = argument == SEPARATOR;
+
+                    runtime_arguments.push(argument);
+
+                    if is_separator {
+                        break;
+                    }
+                }
+
+                if runtime_arguments.last().map(String::as_str) != Some(SEPARATOR) {
+                    return Err(SteamLaunchCommandError::MissingSeparator(entry_point));
+                }
+            }
+
+            runtime = Some(SteamRuntime {
+                entry_point,
+                arguments: runtime_arguments,
+            });
+        }
+
+        let mut proton = None;
+
+        if let Some(executable) = arguments.next_if(|argument| file_name(argument) == "proton") {
+            let verb = arguments
+                .next()
+                .ok_or(SteamLaunchCommandError::MissingGameExecutable)?;
+
+            proton = Some(ProtonInvocation { executable, verb });
+        }
+
+        let game_executable = arguments
+            .next()
+            .ok_or(SteamLaunchCommandError::MissingGameExecutable)?;
+
+        Ok(Self {
+            reaper,
+            app_id,
+            launch_wrapper,
+            runtime,
+            proton,
+            game_executable,
+            game_arguments: arguments.collect(),
+        })
+    }
+
+    // Everything that runs on the host: the reaper, the launch wrapper and the runtime's entry point.
+    pub fn host_argv(&self) -> Vec<String> {
+        let mut argv = self.reaper.clone();
+
+        argv.extend(self.launch_wrapper.iter().cloned());
+
+        if let Some(runtime) = &self.runtime {
+            argv.push(runtime.entry_point.clone());
+            argv.extend(runtime.arguments.iter().cloned());
+        }
+
+        argv
+    }
+
+    // Everything that runs inside the runtime container (if there is one): Proton and the game.
+    pub fn container_argv(&self) -> Vec<String> {
+        let mut argv = Vec::new();
+
+        if let Some(proton) = &self.proton {
+            argv.push(proton.executable.clone());
+            argv.push(proton.verb.clone());
+        }
+
+        argv.push(self.game_executable.clone());
+        argv.extend(self.game_arguments.iter().cloned());
+
+        argv
+    }
+
+    pub fn as_argv(&self) -> Vec<String> {
+        let mut argv = self.host_argv();
+
+        argv.extend(self.container_argv());
+
+        argv
+    }
+}
+
+fn file_name(argument: &str) -> &str {
+    Path::new(argument)
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .unwrap_or(argument)
+}
+
+fn is_runtime_entry_point(argument: &str) -> bool {
+    let file_name = file_name(argument);
+
+    file_name.ends_with("entry-point")
+        || file_name.starts_with("pressure-vessel")
+        || (file_name == "run.sh" && argument.contains("steam-runtime"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SteamLaunchCommand, SteamLaunchCommandError};
+
+    struct Fixture {
+        contents: &'static str,
+        app_id: u32,
+        runtime_entry_point: Option<&'static str>,
+        proton: Option<&'static str>,
+        game_executable: &'static str,
+        game_arguments: &'static [&'static str],
+    }
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/steam_launch_commands/",
+                $name,
+                ".txt"
+            ))
+        };
+    }
+
+    const FIXTURES: &[Fixture] = &[
+        Fixture {
+            contents: fixture!("proton_experimental_sniper"),
+            app_id: 582660,
+            runtime_entry_point: Some("/home/user/.local/share/Steam/steamapps/common/SteamLinuxRuntime_sniper/_v2-entry-point"),
+            proton: Some("/home/user/.local/share/Steam/steamapps/common/Proton - Experimental/proton"),
+            game_executable: "/home/user/.local/share/Steam/steamapps/common/Black Desert Online/BlackDesertLauncher.exe",
+            game_arguments: &["--use-d3d11"],
+        },
+        Fixture {
+            contents: fixture!("ge_proton_compatibilitytools"),
+            app_id: 1245620,
+            runtime_entry_point: Some("/home/user/.steam/steam/steamapps/common/SteamLinuxRuntime_sniper/_v2-entry-point"),
+            proton: Some("/home/user/.steam/root/compatibilitytools.d/GE-Proton9-7/proton"),
+            game_executable: "/home/user/.steam/steam/steamapps/common/ELDEN RING/Game/start_protected_game.exe",
+            game_arguments: &[],
+        },
+        Fixture {
+            contents: fixture!("proton_without_runtime"),
+            app_id: 289070,
+            runtime_entry_point: None,
+            proton: Some("/home/user/.steam/steam/steamapps/common/Proton 5.0/proton"),
+            game_executable: "/home/user/.steam/steam/steamapps/common/Sid Meier's Civilization VI/Base/Binaries/Win64Steam/CivilizationVI.exe",
+            game_arguments: &["%command_line_args%"],
+        },
+        Fixture {
+            contents: fixture!("native_soldier_runtime"),
+            app_id: 730,
+            runtime_entry_point: Some("/home/user/.local/share/Steam/steamapps/common/SteamLinuxRuntime_sniper/_v2-entry-point"),
+            proton: None,
+            game_executable: "/home/user/.local/share/Steam/steamapps/common/Counter-Strike Global Offensive/game/cs2.sh",
+            game_arguments: &["-steam", "-novid"],
+        },
+        Fixture {
+            contents: fixture!("native_scout_runtime"),
+            app_id: 620,
+            runtime_entry_point: Some("/home/user/.local/share/Steam/ubuntu12_32/steam-runtime/run.sh"),
+            proton: None,
+            game_executable: "/home/user/.local/share/Steam/steamapps/common/Portal 2/portal2.sh",
+            game_arguments: &["-game", "portal2"],
+        },
+        Fixture {
+            contents: fixture!("flatpak_proton_pressure_vessel"),
+            app_id: 1091500,
+            runtime_entry_point: Some("/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/common/SteamLinuxRuntime_soldier/pressure-vessel/bin/pressure-vessel-wrap"),
+            proton: Some("/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/common/Proton 8.0/proton"),
+            game_executable: "/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/common/Cyberpunk 2077/bin/x64/Cyberpunk2077.exe",
+            game_arguments: &["--launcher-skip", "-skipStartScreen"],
+        },
+        Fixture {
+            contents: fixture!("native_without_runtime"),
+            app_id: 1145360,
+            runtime_entry_point: None,
+            proton: None,
+            game_executable: "/home/user/.local/share/Steam/steamapps/common/Hades/Hades",
+            game_arguments: &[],
+        },
+        Fixture {
+            contents: fixture!("non_steam_shortcut"),
+            app_id: 3228583970,
+            runtime_entry_point: None,
+            proton: None,
+            game_executable: "/home/user/Games/Heroic/Some Game (2019)/game.x86_64",
+            game_arguments: &["--fullscreen", "--name=$PLAYER"],
+        },
+    ];
+
+    #[test]
+    fn parse_real_world_launch_commands() {
+        for fixture in FIXTURES {
+            let command =
+                SteamLaunchCommand::parse(fixture.contents.trim()).unwrap_or_else(|error| {
+                    panic!("Failed to parse `{}`, see: {error}", fixture.contents)
+                });
+
+            assert_eq!(command.app_id, fixture.app_id);
+
+            assert_eq!(
+                command
+                    .runtime
+                    .as_ref()
+                    .map(|runtime| runtime.entry_point.as_str()),
+                fixture.runtime_entry_point
+            );
+
+            assert_eq!(
+                command
+                    .proton
+                    .as_ref()
+                    .map(|proton| proton.executable.as_str()),
+                fixture.proton
+            );
+
+            if let Some(proton) = &command.proton {
+                assert_eq!(proton.verb, "waitforexitandrun");
+            }
+
+            assert_eq!(command.game_executable, fixture.game_executable);
+            assert_eq!(command.game_arguments, fixture.game_arguments);
+
+            // Splitting the command into its parts shouldn't lose or reorder any argument.
+            let original_arguments = shell_words::split(fixture.contents.trim())
+                .expect("Fixture should've been splittable");
+
+            assert_eq!(command.as_argv(), original_arguments);
+        }
+    }
+
+    #[test]
+    fn runtime_options_stay_on_the_host() {
+        let command = SteamLaunchCommand::parse(fixture!("proton_experimental_sniper").trim())
+            .expect("Failed to parse the fixture");
+
+        let host_argv = command.host_argv();
+
+        assert_eq!(
+            host_argv[host_argv.len() - 2..],
+            ["--verb=waitforexitandrun", "--"]
+        );
+
+        assert_eq!(
+            command.container_argv()[..2],
+            [
+                "/home/user/.local/share/Steam/steamapps/common/Proton - Experimental/proton",
+                "waitforexitandrun"
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_commands_that_only_look_like_steam_launch_commands() {
+        assert!(matches!(
+            SteamLaunchCommand::parse("/usr/bin/game --arg SteamLaunch_AppId=10"),
+            Err(SteamLaunchCommandError::MissingSteamLaunch)
+        ));
+
+        assert!(matches!(
+            SteamLaunchCommand::parse("reaper SteamLaunch AppId=abc -- /usr/bin/game"),
+            Err(SteamLaunchCommandError::MissingAppId)
+        ));
+
+        assert!(matches!(
+            SteamLaunchCommand::parse("echo 'SteamLaunch AppId=10' -- /usr/bin/game"),
+            Err(SteamLaunchCommandError::MissingSteamLaunch)
+        ));
+
+        assert!(matches!(
+            SteamLaunchCommand::parse("reaper SteamLaunch AppId=10 --"),
+            Err(SteamLaunchCommandError::MissingGameExecutable)
+        ));
+    }
+}